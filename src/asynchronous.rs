@@ -0,0 +1,192 @@
+//! `async` test steps, executed on REAPER main thread.
+//!
+//! Future of the step is polled once per tick of the timer, which drives
+//! the suite, so it never blocks REAPER. Futures of this module make the
+//! step wait for REAPER main loop:
+//!
+//! ```ignore
+//! use reaper_test::asynchronous::{sleep, timeout, wait_for_play_position};
+//! use std::time::Duration;
+//!
+//! test.push_test_step(TestStep::asynchronous("playback", |reaper| async move {
+//!     reaper.low().OnPlayButton();
+//!     timeout(Duration::from_secs(3), wait_for_play_position(1.0)).await?;
+//!     reaper.low().OnStopButton();
+//!     sleep(Duration::from_millis(100)).await;
+//!     Ok(())
+//! }));
+//! ```
+
+use crate::{
+    deferred::{DeferredStep, StepContext, StepPoll},
+    ReaperTest, TestStepResult,
+};
+use rea_rs::Reaper;
+use std::{
+    error::Error,
+    fmt::Display,
+    future::Future,
+    pin::Pin,
+    ptr,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+    time::{Duration, Instant},
+};
+
+type StepFuture = Pin<Box<dyn Future<Output = TestStepResult>>>;
+
+/// Futures are polled on every tick anyway, so there is nothing to wake.
+fn noop_raw_waker() -> RawWaker {
+    RawWaker::new(ptr::null(), &NOOP_WAKER_VTABLE)
+}
+static NOOP_WAKER_VTABLE: RawWakerVTable =
+    RawWakerVTable::new(|_| noop_raw_waker(), |_| {}, |_| {}, |_| {});
+
+/// Deferred step, driving the future.
+pub(crate) struct AsyncStep {
+    start: Option<Box<dyn FnOnce(&'static mut Reaper) -> StepFuture>>,
+    future: Option<StepFuture>,
+}
+impl AsyncStep {
+    pub fn new<Fut>(start: impl FnOnce(&'static mut Reaper) -> Fut + 'static) -> Self
+    where
+        Fut: Future<Output = TestStepResult> + 'static,
+    {
+        Self {
+            start: Some(Box::new(move |reaper| -> StepFuture {
+                Box::pin(start(reaper))
+            })),
+            future: None,
+        }
+    }
+}
+impl DeferredStep for AsyncStep {
+    fn poll(&mut self, reaper: &'static mut Reaper, _: &StepContext) -> StepPoll {
+        if let Some(start) = self.start.take() {
+            self.future = Some(start(reaper));
+        }
+        let future = self.future.as_mut().expect("future has been created");
+        let waker = unsafe { Waker::from_raw(noop_raw_waker()) };
+        match future.as_mut().poll(&mut Context::from_waker(&waker)) {
+            Poll::Ready(result) => StepPoll::Ready(result),
+            Poll::Pending => StepPoll::Pending,
+        }
+    }
+}
+
+/// Future, returned by [`next_tick`].
+pub struct NextTick {
+    polled: bool,
+}
+impl Future for NextTick {
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.polled {
+            true => Poll::Ready(()),
+            false => {
+                self.polled = true;
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Let REAPER run its main loop once.
+pub fn next_tick() -> NextTick {
+    NextTick { polled: false }
+}
+
+/// Future, returned by [`sleep`].
+pub struct Sleep {
+    deadline: Instant,
+}
+impl Future for Sleep {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+        match Instant::now() >= self.deadline {
+            true => Poll::Ready(()),
+            false => Poll::Pending,
+        }
+    }
+}
+
+/// Wait for duration without blocking REAPER.
+///
+/// Precision is limited by the timer interval (about 30 ms).
+pub fn sleep(duration: Duration) -> Sleep {
+    Sleep {
+        deadline: Instant::now() + duration,
+    }
+}
+
+/// Future, returned by [`wait_for`].
+pub struct WaitFor<C> {
+    condition: C,
+}
+impl<C> Future for WaitFor<C>
+where
+    C: FnMut(&mut Reaper) -> bool + Unpin,
+{
+    type Output = ();
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+        let reaper = &mut ReaperTest::get_mut().reaper;
+        match (self.condition)(reaper) {
+            true => Poll::Ready(()),
+            false => Poll::Pending,
+        }
+    }
+}
+
+/// Check condition on every tick until it returns `true`.
+///
+/// Never finishes if condition is not met, so usually is combined with
+/// [`timeout`].
+pub fn wait_for<C>(condition: C) -> WaitFor<C>
+where
+    C: FnMut(&mut Reaper) -> bool + Unpin,
+{
+    WaitFor { condition }
+}
+
+/// Wait until play position of the current project reaches `position`
+/// (in seconds).
+pub fn wait_for_play_position(position: f64) -> WaitFor<impl FnMut(&mut Reaper) -> bool + Unpin> {
+    wait_for(move |reaper: &mut Reaper| reaper.low().GetPlayPosition() >= position)
+}
+
+/// Error of [`timeout`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TimedOut(pub Duration);
+impl Display for TimedOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "future has not been finished in {:?}", self.0)
+    }
+}
+impl Error for TimedOut {}
+
+/// Future, returned by [`timeout`].
+pub struct Timeout<F> {
+    future: Pin<Box<F>>,
+    duration: Duration,
+    deadline: Instant,
+}
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, TimedOut>;
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if let Poll::Ready(output) = self.future.as_mut().poll(cx) {
+            return Poll::Ready(Ok(output));
+        }
+        match Instant::now() >= self.deadline {
+            true => Poll::Ready(Err(TimedOut(self.duration))),
+            false => Poll::Pending,
+        }
+    }
+}
+
+/// Fail with [`TimedOut`] if future is not finished in time.
+pub fn timeout<F: Future>(duration: Duration, future: F) -> Timeout<F> {
+    Timeout {
+        future: Box::pin(future),
+        duration,
+        deadline: Instant::now() + duration,
+    }
+}
@@ -0,0 +1,103 @@
+//! Shared cache of REAPER installations.
+//!
+//! Every REAPER version is unpacked once per machine into its own
+//! directory, keyed by version, OS and architecture, so several workspaces
+//! reuse it. Parallel test processes are serialized by a lock file while
+//! one of them downloads and unpacks.
+
+use crate::{
+    integration_test::{setup_reaper_for_linux, setup_reaper_for_macos, Result},
+    plugin::Workspace,
+    IntegrationTestConfig, ReaperVersion,
+};
+use fs2::FileExt;
+use std::{
+    fs::{self, File},
+    path::PathBuf,
+};
+
+/// Environment variable with the cache directory, used if it is not set by
+/// [`IntegrationTestConfig::cache_dir`].
+pub const REAPER_CACHE_ENV: &str = "REAPER_TEST_CACHE";
+
+/// Marker of the finished installation, so half-unpacked REAPER of a killed
+/// process is never used.
+const INSTALLED_MARKER: &str = ".installed";
+
+/// `$XDG_CACHE_HOME/rea-rs-test/reaper` (`~/Library/Caches/...` on macOS).
+///
+/// Falls back to the target dir if home directory is unknown.
+pub(crate) fn default_cache_dir(workspace: &Workspace) -> PathBuf {
+    if let Some(path) = std::env::var_os(REAPER_CACHE_ENV) {
+        return PathBuf::from(path);
+    }
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let base = match cfg!(target_os = "macos") {
+        true => home.map(|home| home.join("Library/Caches")),
+        false => std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home.map(|home| home.join(".cache"))),
+    };
+    match base {
+        Some(base) => base.join("rea-rs-test").join("reaper"),
+        None => workspace.target_dir.join("reaper"),
+    }
+}
+
+fn cache_key(version: &ReaperVersion) -> String {
+    format!(
+        "{}-{}-{}",
+        version,
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    )
+}
+
+/// Returns REAPER executable of the configured version, installing it
+/// into the cache if needed.
+pub(crate) fn cached_reaper_executable(
+    config: &IntegrationTestConfig,
+    workspace: &Workspace,
+) -> Result<PathBuf> {
+    let version = &config.reaper_version;
+    let cache_path = config.cache_dir_path(workspace);
+    let key = cache_key(version);
+    let install_path = cache_path.join(&key);
+    fs::create_dir_all(&cache_path)?;
+    let lock = File::create(cache_path.join(format!("{}.lock", key)))?;
+    if lock.try_lock_exclusive().is_err() {
+        println!(
+            "Waiting for another process, installing REAPER {}...",
+            version
+        );
+        lock.lock_exclusive()?;
+    }
+    // Lock is released when the file is closed.
+    let marker_path = install_path.join(INSTALLED_MARKER);
+    let executable = match cfg!(target_os = "macos") {
+        true => install_path
+            .join(version.macos_download_path())
+            .join(version.macos_executable_path()),
+        false => install_path
+            .join(version.linux_download_path())
+            .join(version.linux_executable_path()),
+    };
+    if marker_path.exists() && executable.exists() {
+        return Ok(executable);
+    }
+    if install_path.exists() {
+        println!(
+            "Removing incomplete REAPER installation {:?}...",
+            install_path
+        );
+        fs::remove_dir_all(&install_path)?;
+    }
+    fs::create_dir_all(&install_path)?;
+    println!("Installing REAPER {} into {:?}...", version, install_path);
+    match cfg!(target_os = "macos") {
+        true => setup_reaper_for_macos(config, &cache_path, &install_path)?,
+        false => setup_reaper_for_linux(config, &cache_path, &install_path)?,
+    };
+    fs::write(marker_path, version.to_string())?;
+    Ok(executable)
+}
@@ -0,0 +1,327 @@
+//! Settings of the integration test launcher.
+
+use crate::{
+    cache::default_cache_dir,
+    download::DownloadSource,
+    integration_test::{run_in_reaper, Result},
+    plugin::Workspace,
+    IntegrationError, ReaperConfig, ReaperVersion, Resource, TestFilter, TestReport,
+};
+use log::LevelFilter;
+use std::{ffi::OsString, path::PathBuf, time::Duration};
+
+/// Environment variable with path of an existing REAPER executable or
+/// installation directory. See [`IntegrationTestConfig::reaper_executable`].
+pub const REAPER_EXECUTABLE_ENV: &str = "REAPER_TEST_EXECUTABLE";
+
+/// Describes how to set up and launch REAPER for the integration test.
+///
+/// ```ignore
+/// use reaper_test::{IntegrationTestConfig, ReaperVersion};
+/// use std::time::Duration;
+///
+/// #[test]
+/// fn main() {
+///     IntegrationTestConfig::new(ReaperVersion::latest())
+///         .timeout(Duration::from_secs(300))
+///         .env("MY_PLUGIN_MODE", "test")
+///         .profile("release")
+///         .run();
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct IntegrationTestConfig {
+    pub(crate) reaper_version: ReaperVersion,
+    pub(crate) filter: TestFilter,
+    pub(crate) timeout: Duration,
+    pub(crate) args: Vec<OsString>,
+    pub(crate) env: Vec<(OsString, OsString)>,
+    pub(crate) log_level: LevelFilter,
+    pub(crate) target_dir: Option<PathBuf>,
+    pub(crate) profile: String,
+    pub(crate) target: Option<String>,
+    pub(crate) plugin_name: Option<String>,
+    pub(crate) build_plugin: bool,
+    pub(crate) features: Vec<String>,
+    pub(crate) resource_dir: Option<PathBuf>,
+    pub(crate) reaper_executable: Option<PathBuf>,
+    pub(crate) download_source: Option<DownloadSource>,
+    pub(crate) checksum_file: Option<PathBuf>,
+    pub(crate) cache_dir: Option<PathBuf>,
+    pub(crate) reaper_config: ReaperConfig,
+    pub(crate) resources: Vec<Resource>,
+    pub(crate) virtual_display: bool,
+}
+impl IntegrationTestConfig {
+    pub fn new(reaper_version: ReaperVersion) -> Self {
+        Self {
+            reaper_version,
+            filter: TestFilter::default(),
+            timeout: Duration::from_secs(120),
+            args: Vec::new(),
+            env: Vec::new(),
+            log_level: LevelFilter::Debug,
+            target_dir: None,
+            profile: "dev".to_string(),
+            target: None,
+            plugin_name: None,
+            build_plugin: true,
+            features: Vec::new(),
+            resource_dir: None,
+            reaper_executable: None,
+            download_source: None,
+            checksum_file: None,
+            cache_dir: None,
+            reaper_config: ReaperConfig::default(),
+            resources: Vec::new(),
+            virtual_display: true,
+        }
+    }
+
+    /// Runs only steps, matching the filter.
+    pub fn filter(mut self, filter: TestFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Time, after which REAPER is killed, if the suite has not finished.
+    ///
+    /// Default is 2 minutes.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Additional command line argument of REAPER.
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn args<I>(mut self, args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString>,
+    {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Additional environment variable of REAPER process.
+    pub fn env(mut self, key: impl Into<OsString>, value: impl Into<OsString>) -> Self {
+        self.env.push((key.into(), value.into()));
+        self
+    }
+
+    /// Level of `log` records inside REAPER (passed as `RUST_LOG`).
+    ///
+    /// Default is `Debug`.
+    pub fn log_level(mut self, level: LevelFilter) -> Self {
+        self.log_level = level;
+        self
+    }
+
+    /// Cargo target directory, where the plugin is built and reports are
+    /// written.
+    ///
+    /// Default is the target directory of the workspace, as reported by
+    /// `cargo metadata`.
+    pub fn target_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.target_dir = Some(path.into());
+        self
+    }
+
+    /// Cargo profile, the plugin is built with. Default is `dev`.
+    pub fn profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = profile.into();
+        self
+    }
+
+    /// Target triple, the plugin is built for.
+    ///
+    /// Default is taken from `CARGO_BUILD_TARGET`. If not set, plugin is
+    /// looked for in the host directory of the target dir.
+    pub fn target(mut self, triple: impl Into<String>) -> Self {
+        self.target = Some(triple.into());
+        self
+    }
+
+    /// Name of the plugin library, as in `[lib]` section of its manifest.
+    ///
+    /// By default it is the `cdylib` target of the package, which runs
+    /// the test, or the only `cdylib` of the workspace.
+    pub fn plugin_name(mut self, name: impl Into<String>) -> Self {
+        self.plugin_name = Some(name.into());
+        self
+    }
+
+    /// `reaper.ini` and other files, REAPER is started with.
+    pub fn reaper_config(mut self, config: ReaperConfig) -> Self {
+        self.reaper_config = config;
+        self
+    }
+
+    /// File or directory, installed into the resource directory before
+    /// launch, e.g. JSFX or a helper extension.
+    pub fn resource(mut self, resource: Resource) -> Self {
+        self.resources.push(resource);
+        self
+    }
+
+    pub fn resources(mut self, resources: impl IntoIterator<Item = Resource>) -> Self {
+        self.resources.extend(resources);
+        self
+    }
+
+    /// Whether to start Xvfb on Linux, if `DISPLAY` is not set.
+    ///
+    /// Default is `true`. Without display REAPER can not start.
+    pub fn virtual_display(mut self, start: bool) -> Self {
+        self.virtual_display = start;
+        self
+    }
+
+    /// Whether to run `cargo build` for the plugin before the test.
+    ///
+    /// Default is `true`. If turned off, the test fails when the built
+    /// plugin is older than its sources.
+    pub fn build_plugin(mut self, build: bool) -> Self {
+        self.build_plugin = build;
+        self
+    }
+
+    /// Features, the plugin is built with.
+    pub fn features<I>(mut self, features: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        self.features.extend(features.into_iter().map(Into::into));
+        self
+    }
+
+    /// Directory, where REAPER keeps its resources (`reaper.ini`,
+    /// `UserPlugins` etc.) during the test.
+    ///
+    /// Every run gets a fresh `run-*` subdir, which is deleted after
+    /// success and kept on failure for inspection.
+    ///
+    /// Default is `reaper` dir inside the target dir.
+    pub fn resource_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.resource_dir = Some(path.into());
+        self
+    }
+
+    /// Uses REAPER, installed on the machine, instead of downloading it.
+    ///
+    /// Path is either the executable or the installation directory (or
+    /// `REAPER.app` bundle on macOS). REAPER is started with own resource
+    /// dir (see [`resource_dir`](Self::resource_dir)), so configuration of
+    /// the user is not touched.
+    ///
+    /// Can be also set by [`REAPER_EXECUTABLE_ENV`] variable.
+    pub fn reaper_executable(mut self, path: impl Into<PathBuf>) -> Self {
+        self.reaper_executable = Some(path.into());
+        self
+    }
+
+    /// Where to take REAPER archive of the configured version from.
+    ///
+    /// This is the per-version setting: config is made for a single
+    /// [`ReaperVersion`], so versions, which are kept in different places,
+    /// are tested with separate configs. Global default is taken from
+    /// [`REAPER_MIRROR_ENV`](crate::REAPER_MIRROR_ENV) or is the official
+    /// site.
+    pub fn download_source(mut self, source: DownloadSource) -> Self {
+        self.download_source = Some(source);
+        self
+    }
+
+    /// File with SHA-256 digests of REAPER archives in the format of
+    /// `sha256sum` output, e.g.
+    /// `sha256sum reaper715_linux_x86_64.tar.xz > reaper.sha256`.
+    ///
+    /// Digest of [`ReaperVersion::with_sha256`] takes precedence.
+    pub fn checksum_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.checksum_file = Some(path.into());
+        self
+    }
+
+    /// Directory, where downloaded REAPER versions are kept.
+    ///
+    /// Default is taken from [`REAPER_CACHE_ENV`](crate::REAPER_CACHE_ENV)
+    /// or is `rea-rs-test/reaper` in the user cache dir, so REAPER is
+    /// shared by all workspaces.
+    pub fn cache_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(path.into());
+        self
+    }
+
+    pub(crate) fn cache_dir_path(&self, workspace: &Workspace) -> PathBuf {
+        match &self.cache_dir {
+            Some(path) => path.clone(),
+            None => default_cache_dir(workspace),
+        }
+    }
+
+    pub(crate) fn download_source_or_default(&self) -> DownloadSource {
+        self.download_source
+            .clone()
+            .unwrap_or_else(DownloadSource::from_env)
+    }
+
+    pub(crate) fn reaper_executable_path(&self) -> Option<PathBuf> {
+        self.reaper_executable
+            .clone()
+            .or_else(|| std::env::var_os(REAPER_EXECUTABLE_ENV).map(PathBuf::from))
+    }
+
+    pub(crate) fn target_triple(&self) -> Option<String> {
+        self.target
+            .clone()
+            .or_else(|| std::env::var("CARGO_BUILD_TARGET").ok())
+    }
+
+    pub(crate) fn resource_dir_path(&self, workspace: &Workspace) -> PathBuf {
+        match &self.resource_dir {
+            Some(path) => path.clone(),
+            None => workspace.target_dir.join("reaper"),
+        }
+    }
+
+    /// Runs the test suite inside REAPER and returns what the plugin
+    /// reported.
+    ///
+    /// On unsupported platforms the run is skipped with a message and
+    /// empty report is returned.
+    ///
+    /// # Panics
+    ///
+    /// If REAPER can not be set up or launched, or if any test step fails.
+    pub fn run(&self) -> TestReport {
+        match self.try_run() {
+            Ok(report) => report,
+            Err(err @ IntegrationError::UnsupportedPlatform) => {
+                println!("{}, skipped", err);
+                TestReport::default()
+            }
+            Err(err) => panic!("{}", err),
+        }
+    }
+
+    /// Runs the test suite inside REAPER without panicking.
+    ///
+    /// Returns [`IntegrationError::TestsFailed`] if any step or hook failed.
+    pub fn try_run(&self) -> Result<TestReport> {
+        let report = run_in_reaper(self, self.filter.to_env())?;
+        match report.is_success() {
+            true => Ok(report),
+            false => Err(IntegrationError::TestsFailed(Box::new(report))),
+        }
+    }
+}
+impl Default for IntegrationTestConfig {
+    fn default() -> Self {
+        Self::new(ReaperVersion::latest())
+    }
+}
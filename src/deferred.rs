@@ -0,0 +1,180 @@
+//! Test steps, which span several ticks of REAPER main loop.
+//!
+//! A sync [`TestStep`](crate::TestStep) runs from start to end in one timer
+//! tick, so it never sees UI refresh, deferred callbacks, timers or
+//! playback position changes. Deferred step is polled once per tick until
+//! it is ready, letting REAPER run its main loop in between.
+//!
+//! ```ignore
+//! use std::time::Duration;
+//! use reaper_test::*;
+//!
+//! test.push_test_step(TestStep::deferred("playback starts", || {
+//!     (|reaper: &'static mut Reaper, _: &StepContext| {
+//!         reaper.low().OnPlayButton();
+//!         StepPoll::Ready(Ok(()))
+//!     })
+//!     .then(wait_until(Duration::from_secs(2), |reaper| {
+//!         reaper.low().GetPlayPosition() > 1.0
+//!     }))
+//! }));
+//! ```
+
+use crate::TestStepResult;
+use rea_rs::Reaper;
+use std::time::{Duration, Instant};
+
+/// State of deferred step after being polled.
+#[derive(Debug)]
+pub enum StepPoll {
+    /// Step should be polled again on the next tick.
+    Pending,
+    /// Step is finished.
+    Ready(TestStepResult),
+}
+
+/// Information about running deferred step.
+#[derive(Debug, Clone)]
+pub struct StepContext {
+    tick: u32,
+    started: Instant,
+}
+impl StepContext {
+    pub(crate) fn new() -> Self {
+        Self {
+            tick: 0,
+            started: Instant::now(),
+        }
+    }
+
+    pub(crate) fn advance(&mut self) {
+        self.tick += 1;
+    }
+
+    /// Number of ticks passed since the step start. `0` on the first poll.
+    pub fn tick(&self) -> u32 {
+        self.tick
+    }
+
+    /// Time passed since the step start.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+/// Test step, which is polled on every timer tick until it is ready.
+///
+/// Implemented for closures `FnMut(&'static mut Reaper, &StepContext) ->
+/// StepPoll`, so either poll-style closure or explicit state machine can be
+/// used.
+pub trait DeferredStep {
+    fn poll(&mut self, reaper: &'static mut Reaper, context: &StepContext) -> StepPoll;
+
+    /// Run `next` after this step finished successfully.
+    fn then<B: DeferredStep>(self, next: B) -> Then<Self, B>
+    where
+        Self: Sized,
+    {
+        Then {
+            first: Some(self),
+            second: next,
+        }
+    }
+}
+impl<F> DeferredStep for F
+where
+    F: FnMut(&'static mut Reaper, &StepContext) -> StepPoll,
+{
+    fn poll(&mut self, reaper: &'static mut Reaper, context: &StepContext) -> StepPoll {
+        self(reaper, context)
+    }
+}
+
+/// Two deferred steps, run one after another.
+///
+/// Created by [`DeferredStep::then`].
+pub struct Then<A, B> {
+    first: Option<A>,
+    second: B,
+}
+impl<A: DeferredStep, B: DeferredStep> DeferredStep for Then<A, B> {
+    fn poll(&mut self, reaper: &'static mut Reaper, context: &StepContext) -> StepPoll {
+        if let Some(first) = self.first.as_mut() {
+            match first.poll(reaper, context) {
+                StepPoll::Ready(Ok(())) => self.first = None,
+                poll => return poll,
+            }
+            // Give REAPER a tick between steps, as if they were separate.
+            return StepPoll::Pending;
+        }
+        self.second.poll(reaper, context)
+    }
+}
+
+/// Waits until condition is met, failing after timeout.
+///
+/// Created by [`wait_until`].
+pub struct WaitUntil<C> {
+    timeout: Duration,
+    deadline: Option<Instant>,
+    condition: C,
+}
+impl<C> DeferredStep for WaitUntil<C>
+where
+    C: FnMut(&mut Reaper) -> bool,
+{
+    fn poll(&mut self, reaper: &'static mut Reaper, _: &StepContext) -> StepPoll {
+        let deadline = *self
+            .deadline
+            .get_or_insert_with(|| Instant::now() + self.timeout);
+        if (self.condition)(reaper) {
+            StepPoll::Ready(Ok(()))
+        } else if Instant::now() >= deadline {
+            StepPoll::Ready(Err(format!(
+                "condition has not been met in {:?}",
+                self.timeout
+            )
+            .into()))
+        } else {
+            StepPoll::Pending
+        }
+    }
+}
+
+/// Poll condition on every tick until it returns `true`.
+///
+/// Step fails if condition is not met during `timeout`, counting from the
+/// first poll.
+pub fn wait_until<C>(timeout: Duration, condition: C) -> WaitUntil<C>
+where
+    C: FnMut(&mut Reaper) -> bool,
+{
+    WaitUntil {
+        timeout,
+        deadline: None,
+        condition,
+    }
+}
+
+/// Waits for the given number of ticks.
+///
+/// Created by [`wait_ticks`].
+pub struct WaitTicks {
+    left: u32,
+}
+impl DeferredStep for WaitTicks {
+    fn poll(&mut self, _: &'static mut Reaper, _: &StepContext) -> StepPoll {
+        match self.left {
+            0 => StepPoll::Ready(Ok(())),
+            _ => {
+                self.left -= 1;
+                StepPoll::Pending
+            }
+        }
+    }
+}
+
+/// Let REAPER run its main loop `ticks` times.
+pub fn wait_ticks(ticks: u32) -> WaitTicks {
+    WaitTicks { left: ticks }
+}
@@ -0,0 +1,110 @@
+//! Virtual display for REAPER on headless Linux machines.
+//!
+//! REAPER can not start without X server. If `DISPLAY` is not set, a
+//! private Xvfb is started on a free display number for the time of the
+//! run.
+
+use crate::{integration_test::Result, IntegrationError};
+use std::{
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+use wait_timeout::ChildExt;
+
+/// Display numbers, which are tried for Xvfb.
+const DISPLAY_NUMBERS: std::ops::Range<u32> = 99..199;
+
+/// Time, given to Xvfb to open its socket.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Running Xvfb server. Killed on drop.
+pub(crate) struct VirtualDisplay {
+    server: Child,
+    name: String,
+}
+impl VirtualDisplay {
+    /// Starts Xvfb on Linux, if `DISPLAY` is not set.
+    pub(crate) fn start_if_needed() -> Result<Option<Self>> {
+        let has_display = std::env::var_os("DISPLAY").is_some_and(|display| !display.is_empty());
+        if !cfg!(target_os = "linux") || has_display {
+            return Ok(None);
+        }
+        println!("DISPLAY is not set, starting Xvfb...");
+        Self::start().map(Some)
+    }
+
+    fn start() -> Result<Self> {
+        for number in DISPLAY_NUMBERS {
+            if display_is_taken(number) {
+                continue;
+            }
+            let mut server = Command::new("Xvfb")
+                .arg(format!(":{}", number))
+                .args(["-screen", "0", "1280x1024x24", "-nolisten", "tcp"])
+                .stdin(Stdio::null())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .map_err(|err| {
+                    IntegrationError::NoVirtualDisplay(format!(
+                        "DISPLAY is not set and Xvfb can not be started: {}",
+                        err
+                    ))
+                })?;
+            // Another process may have taken the same number in between,
+            // then Xvfb exits and the next number is tried.
+            let started = Instant::now();
+            while started.elapsed() < STARTUP_TIMEOUT {
+                if server.try_wait()?.is_some() {
+                    break;
+                }
+                if socket_path(number).exists() {
+                    let name = format!(":{}", number);
+                    println!("Xvfb is running on display {}", name);
+                    return Ok(Self { server, name });
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+            let _ = server.kill();
+            let _ = server.wait();
+        }
+        Err(IntegrationError::NoVirtualDisplay(
+            "DISPLAY is not set and Xvfb could not be started on any free display".to_string(),
+        ))
+    }
+
+    /// Value for `DISPLAY` variable, e.g. `:99`.
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+}
+impl Drop for VirtualDisplay {
+    fn drop(&mut self) {
+        // SIGTERM lets Xvfb remove its lock file and socket.
+        let terminated = Command::new("kill")
+            .arg("-TERM")
+            .arg(self.server.id().to_string())
+            .status()
+            .is_ok_and(|status| status.success());
+        if terminated
+            && matches!(
+                self.server.wait_timeout(Duration::from_secs(2)),
+                Ok(Some(_))
+            )
+        {
+            return;
+        }
+        let _ = self.server.kill();
+        let _ = self.server.wait();
+    }
+}
+
+fn socket_path(number: u32) -> PathBuf {
+    PathBuf::from(format!("/tmp/.X11-unix/X{}", number))
+}
+
+fn display_is_taken(number: u32) -> bool {
+    Path::new(&format!("/tmp/.X{}-lock", number)).exists() || socket_path(number).exists()
+}
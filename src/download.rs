@@ -0,0 +1,387 @@
+//! Sources of REAPER archives.
+//!
+//! By default archives are downloaded from the official site. Machines
+//! without internet access can take them from a local directory or from a
+//! mirror, which keeps the layout of the official `files` directory.
+
+use crate::{
+    integration_test::Result,
+    version::{parse_sha256, sha256_to_hex},
+    IntegrationError, IntegrationTestConfig, ReaperVersion,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Environment variable with the download source, used if it is not set by
+/// [`IntegrationTestConfig::download_source`](crate::IntegrationTestConfig::download_source).
+///
+/// Parsed by [`DownloadSource::parse`].
+pub const REAPER_MIRROR_ENV: &str = "REAPER_TEST_MIRROR";
+
+/// Base URL of the official REAPER archives.
+pub const OFFICIAL_URL: &str = "https://www.reaper.fm/files";
+
+/// Where REAPER archives are taken from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DownloadSource {
+    /// `https://www.reaper.fm/files`.
+    #[default]
+    Official,
+    /// Directory with archives, named as on the official site, e.g.
+    /// `reaper715_linux_x86_64.tar.xz`.
+    LocalDir(PathBuf),
+    /// URL prefix (`http://`, `https://` or `file://`), which replaces
+    /// [`OFFICIAL_URL`], e.g. `http://mirror.local/reaper` for
+    /// `http://mirror.local/reaper/7.x/reaper715_linux_x86_64.tar.xz`.
+    Mirror(String),
+}
+impl DownloadSource {
+    /// Source, given by [`REAPER_MIRROR_ENV`], or the official site.
+    pub fn from_env() -> Self {
+        match std::env::var(REAPER_MIRROR_ENV) {
+            Ok(location) if !location.is_empty() => Self::parse(&location),
+            _ => Self::Official,
+        }
+    }
+
+    /// URL is taken as a mirror, anything else as a local directory.
+    pub fn parse(location: &str) -> Self {
+        match location.contains("://") {
+            true => Self::Mirror(location.trim_end_matches('/').to_string()),
+            false => Self::LocalDir(PathBuf::from(location)),
+        }
+    }
+
+    /// URL or path of the archive.
+    pub fn location(&self, version: &ReaperVersion, archive_name: &str) -> String {
+        match self {
+            Self::Official => format!("{}/{}", OFFICIAL_URL, version.archive_path(archive_name)),
+            Self::Mirror(prefix) => format!("{}/{}", prefix, version.archive_path(archive_name)),
+            Self::LocalDir(dir) => dir.join(archive_name).display().to_string(),
+        }
+    }
+
+    /// Puts the archive to `dest_file_path`.
+    ///
+    /// Archive is written into `*.part` file first and renamed when it is
+    /// complete, so a killed process never leaves truncated archive behind.
+    pub(crate) fn fetch(
+        &self,
+        version: &ReaperVersion,
+        archive_name: &str,
+        dest_file_path: &Path,
+    ) -> Result<()> {
+        let location = self.location(version, archive_name);
+        if let Some(parent) = dest_file_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut part_name = dest_file_path.file_name().unwrap_or_default().to_owned();
+        part_name.push(".part");
+        let part_path = dest_file_path.with_file_name(part_name);
+        let fetched = match (self, location.strip_prefix("file://")) {
+            (Self::LocalDir(_), _) => copy_archive(Path::new(&location), &part_path),
+            (_, Some(path)) => copy_archive(Path::new(path), &part_path),
+            (_, None) => download(&location, &part_path),
+        };
+        if let Err(err) = fetched {
+            let _ = fs::remove_file(&part_path);
+            return Err(err);
+        }
+        fs::rename(&part_path, dest_file_path)?;
+        Ok(())
+    }
+}
+
+fn copy_archive(source_path: &Path, dest_file_path: &Path) -> Result<()> {
+    println!("Copying REAPER archive from {:?}...", source_path);
+    fs::copy(source_path, dest_file_path).map_err(|err| IntegrationError::DownloadFailed {
+        url: source_path.display().to_string(),
+        source: err.into(),
+    })?;
+    Ok(())
+}
+
+fn download(url: &str, dest_file_path: &Path) -> Result<()> {
+    println!("Downloading REAPER from {}...", url);
+    let failed =
+        |source: Box<dyn std::error::Error + Send + Sync>| IntegrationError::DownloadFailed {
+            url: url.to_string(),
+            source,
+        };
+    let mut response = reqwest::blocking::get(url)
+        .and_then(|response| response.error_for_status())
+        .map_err(|err| failed(err.into()))?;
+    let mut dest_file = fs::File::create(dest_file_path)?;
+    io::copy(&mut response, &mut dest_file).map_err(|err| failed(err.into()))?;
+    Ok(())
+}
+
+/// Checks SHA-256 digest of the archive, expected by the REAPER version or
+/// by the checksum file.
+///
+/// Corrupted archive is deleted, so it is fetched again next time.
+/// Archive without known digest is accepted.
+pub(crate) fn verify_archive(config: &IntegrationTestConfig, archive_path: &Path) -> Result<()> {
+    let archive_name = archive_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let expected = match config.reaper_version.sha256() {
+        Some(digest) => Some(digest),
+        None => match &config.checksum_file {
+            Some(path) => read_checksum_file(path)?.remove(&archive_name),
+            None => None,
+        },
+    };
+    let expected = match expected {
+        Some(digest) => digest,
+        None => {
+            println!(
+                "No SHA-256 digest is known for {}, not verified",
+                archive_name
+            );
+            return Ok(());
+        }
+    };
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(archive_path)?, &mut hasher)?;
+    let actual: [u8; 32] = hasher.finalize().into();
+    if actual != expected {
+        let _ = fs::remove_file(archive_path);
+        return Err(IntegrationError::ChecksumMismatch {
+            path: archive_path.to_path_buf(),
+            expected: sha256_to_hex(&expected),
+            actual: sha256_to_hex(&actual),
+        });
+    }
+    Ok(())
+}
+
+/// Reads digests in the format of `sha256sum` output: `<digest>  <file>`
+/// per line. Empty lines and lines, started with `#`, are skipped.
+fn read_checksum_file(path: &Path) -> Result<HashMap<String, [u8; 32]>> {
+    let invalid = |line: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{:?}: invalid checksum line {:?}", path, line),
+        )
+    };
+    let mut digests = HashMap::new();
+    for line in fs::read_to_string(path)?.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (digest, name) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| invalid(line))?;
+        let digest = parse_sha256(digest).ok_or_else(|| invalid(line))?;
+        // `*` marks binary mode in `sha256sum` output.
+        digests.insert(name.trim().trim_start_matches('*').to_string(), digest);
+    }
+    Ok(digests)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    const ARCHIVE: &str = "reaper715_linux_x86_64.tar.xz";
+
+    /// Serves `files` (request path and body) over HTTP, anything else is
+    /// answered by 404. Returns base URL.
+    fn serve(files: Vec<(&'static str, &'static [u8])>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request = String::new();
+                reader.read_line(&mut request).unwrap();
+                loop {
+                    let mut header = String::new();
+                    if reader.read_line(&mut header).unwrap() == 0 || header == "\r\n" {
+                        break;
+                    }
+                }
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let (status, body) = match files.iter().find(|(file, _)| *file == path) {
+                    Some((_, body)) => ("200 OK", *body),
+                    None => ("404 Not Found", &b"not found"[..]),
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(body).unwrap();
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    #[test]
+    fn parse_location() {
+        assert_eq!(
+            DownloadSource::parse("http://mirror.local/reaper/"),
+            DownloadSource::Mirror("http://mirror.local/reaper".into())
+        );
+        assert_eq!(
+            DownloadSource::parse("file:///srv/reaper"),
+            DownloadSource::Mirror("file:///srv/reaper".into())
+        );
+        assert_eq!(
+            DownloadSource::parse("/srv/reaper"),
+            DownloadSource::LocalDir("/srv/reaper".into())
+        );
+    }
+
+    #[test]
+    fn location_of_archive() {
+        let version = ReaperVersion::new(7, 15);
+        assert_eq!(
+            DownloadSource::Official.location(&version, ARCHIVE),
+            format!("https://www.reaper.fm/files/7.x/{}", ARCHIVE)
+        );
+        assert_eq!(
+            DownloadSource::Mirror("http://mirror.local/reaper".into()).location(&version, ARCHIVE),
+            format!("http://mirror.local/reaper/7.x/{}", ARCHIVE)
+        );
+        assert_eq!(
+            DownloadSource::LocalDir("/srv/reaper".into()).location(&version, ARCHIVE),
+            Path::new("/srv/reaper").join(ARCHIVE).display().to_string()
+        );
+    }
+
+    #[test]
+    fn fetch_from_http_mirror() {
+        let base = serve(vec![(
+            "/reaper/7.x/reaper715_linux_x86_64.tar.xz",
+            b"archive",
+        )]);
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("archives").join(ARCHIVE);
+        DownloadSource::Mirror(format!("{}/reaper", base))
+            .fetch(&ReaperVersion::new(7, 15), ARCHIVE, &dest)
+            .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"archive");
+    }
+
+    #[test]
+    fn fetch_fails_on_http_404() {
+        let base = serve(Vec::new());
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join(ARCHIVE);
+        let error = DownloadSource::Mirror(base.clone())
+            .fetch(&ReaperVersion::new(7, 15), ARCHIVE, &dest)
+            .unwrap_err();
+        match error {
+            IntegrationError::DownloadFailed { url, source } => {
+                assert_eq!(url, format!("{}/7.x/{}", base, ARCHIVE));
+                assert!(source.to_string().contains("404"), "{}", source);
+            }
+            error => panic!("unexpected error: {}", error),
+        }
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn fetch_from_file_url() {
+        let mirror = tempfile::tempdir().unwrap();
+        fs::create_dir(mirror.path().join("7.x")).unwrap();
+        fs::write(mirror.path().join("7.x").join(ARCHIVE), b"archive").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join(ARCHIVE);
+        DownloadSource::parse(&format!("file://{}", mirror.path().display()))
+            .fetch(&ReaperVersion::new(7, 15), ARCHIVE, &dest)
+            .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"archive");
+    }
+
+    #[test]
+    fn fetch_from_local_dir() {
+        let source = tempfile::tempdir().unwrap();
+        fs::write(source.path().join(ARCHIVE), b"archive").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join(ARCHIVE);
+        let local = DownloadSource::LocalDir(source.path().to_path_buf());
+        local
+            .fetch(&ReaperVersion::new(7, 15), ARCHIVE, &dest)
+            .unwrap();
+        assert_eq!(fs::read(&dest).unwrap(), b"archive");
+
+        let missing = dir.path().join("reaper722_linux_x86_64.tar.xz");
+        let error = local
+            .fetch(
+                &ReaperVersion::new(7, 22),
+                "reaper722_linux_x86_64.tar.xz",
+                &missing,
+            )
+            .unwrap_err();
+        assert!(matches!(error, IntegrationError::DownloadFailed { .. }));
+        assert!(!missing.exists());
+        assert!(!dir
+            .path()
+            .join("reaper722_linux_x86_64.tar.xz.part")
+            .exists());
+    }
+
+    const DIGEST: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    fn checksum_file(content: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn read_checksum_file_in_sha256sum_format() {
+        let file = checksum_file(&format!(
+            "# REAPER archives\n\
+             \n\
+             {digest}  reaper715_linux_x86_64.tar.xz\n\
+             {digest} *reaper715_universal.dmg\n",
+            digest = DIGEST
+        ));
+        let digests = read_checksum_file(file.path()).unwrap();
+        let expected = parse_sha256(DIGEST).unwrap();
+        assert_eq!(digests.len(), 2);
+        assert_eq!(
+            digests.get("reaper715_linux_x86_64.tar.xz"),
+            Some(&expected)
+        );
+        assert_eq!(digests.get("reaper715_universal.dmg"), Some(&expected));
+    }
+
+    #[test]
+    fn read_checksum_file_rejects_malformed_lines() {
+        for line in [
+            "reaper715_linux_x86_64.tar.xz".to_string(),
+            "abc  reaper715_linux_x86_64.tar.xz".to_string(),
+            format!(
+                "{}  reaper715_linux_x86_64.tar.xz",
+                DIGEST.replace('a', "g")
+            ),
+        ] {
+            let file = checksum_file(&line);
+            let error = read_checksum_file(file.path()).unwrap_err();
+            assert!(
+                error.to_string().contains("invalid checksum line"),
+                "{}",
+                error
+            );
+        }
+    }
+}
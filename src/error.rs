@@ -0,0 +1,148 @@
+//! Errors of the integration test launcher.
+
+use crate::{integration_test::failure_message, TestReport};
+use std::{error::Error, fmt::Display, io, path::PathBuf};
+
+/// Reason, why [`try_run_integration_test`](crate::try_run_integration_test)
+/// has not succeeded.
+///
+/// Variants, which happened after the suite has been started, carry
+/// everything the plugin managed to report.
+#[derive(Debug)]
+pub enum IntegrationError {
+    /// REAPER archive can not be downloaded.
+    DownloadFailed {
+        url: String,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// Downloaded REAPER can not be unpacked or installed.
+    UnpackFailed {
+        path: PathBuf,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    /// Downloaded archive does not match its SHA-256 digest. The archive
+    /// is deleted.
+    ChecksumMismatch {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+    /// Test plugin has not been built.
+    PluginNotFound(PathBuf),
+    /// Sources of the test plugin are newer, than the built library.
+    PluginOutdated(PathBuf),
+    /// Source of [`Resource`](crate::Resource) does not exist.
+    ResourceNotFound(PathBuf),
+    /// `cargo` invocation failed or its output is not understood.
+    CargoFailed(String),
+    /// Existing REAPER installation is not found at the path.
+    ReaperNotFound(PathBuf),
+    /// REAPER executable can not be started.
+    LaunchFailed(io::Error),
+    /// There is no display and virtual one can not be started.
+    NoVirtualDisplay(String),
+    /// REAPER has been killed, because the suite or one of its steps
+    /// didn't finish in time.
+    ReaperTimedOut(Box<TestReport>),
+    /// REAPER exited before the suite end, e.g. crashed.
+    ReaperCrashed {
+        /// Signal, which terminated REAPER (only on Unix).
+        signal: Option<i32>,
+        code: Option<i32>,
+        report: Box<TestReport>,
+    },
+    /// Suite has been run, but some steps or hooks failed.
+    TestsFailed(Box<TestReport>),
+    /// Integration tests can not be run on this OS.
+    UnsupportedPlatform,
+    Io(io::Error),
+}
+impl IntegrationError {
+    /// Report of the suite, if it has been started.
+    pub fn report(&self) -> Option<&TestReport> {
+        match self {
+            Self::ReaperTimedOut(report)
+            | Self::ReaperCrashed { report, .. }
+            | Self::TestsFailed(report) => Some(report),
+            _ => None,
+        }
+    }
+}
+impl Display for IntegrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DownloadFailed { url, source } => {
+                write!(f, "Can not download REAPER from {}: {}", url, source)
+            }
+            Self::UnpackFailed { path, source } => {
+                write!(f, "Can not unpack REAPER from {:?}: {}", path, source)
+            }
+            Self::ChecksumMismatch {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "REAPER archive {:?} is corrupted (SHA-256 is {}, expected {}), it is deleted",
+                path, actual, expected
+            ),
+            Self::PluginNotFound(path) => write!(
+                f,
+                "Test plugin is not found at {:?}, is it built with `cargo build`?",
+                path
+            ),
+            Self::PluginOutdated(path) => write!(
+                f,
+                "Test plugin {:?} is older, than its sources, rebuild it with `cargo build`",
+                path
+            ),
+            Self::ResourceNotFound(path) => write!(f, "Resource {:?} is not found", path),
+            Self::CargoFailed(message) => write!(f, "{}", message),
+            Self::ReaperNotFound(path) => write!(f, "REAPER executable {:?} is not found", path),
+            Self::LaunchFailed(err) => write!(f, "Can not start REAPER: {}", err),
+            Self::NoVirtualDisplay(message) => write!(f, "{}", message),
+            Self::ReaperTimedOut(report) if report.steps.is_empty() => write!(
+                f,
+                "REAPER didn't exit in time (maybe integration test has not started at all)"
+            ),
+            Self::ReaperCrashed {
+                signal,
+                code,
+                report,
+            } if report.steps.is_empty() => {
+                write!(f, "REAPER exited (")?;
+                match (signal, code) {
+                    (Some(signal), _) => write!(f, "signal: {}", signal)?,
+                    (None, Some(code)) => write!(f, "exit code: {}", code)?,
+                    (None, None) => write!(f, "unknown status")?,
+                }
+                write!(f, ") without running the integration test")
+            }
+            Self::ReaperTimedOut(report)
+            | Self::ReaperCrashed { report, .. }
+            | Self::TestsFailed(report) => write!(f, "{}", failure_message(report)),
+            Self::UnsupportedPlatform => write!(
+                f,
+                "REAPER integration tests currently not supported on {}",
+                std::env::consts::OS
+            ),
+            Self::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+impl Error for IntegrationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::DownloadFailed { source, .. } | Self::UnpackFailed { source, .. } => {
+                Some(source.as_ref())
+            }
+            Self::LaunchFailed(err) | Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+impl From<io::Error> for IntegrationError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
@@ -0,0 +1,138 @@
+//! Selection of test steps to run, forwarded from the launcher into REAPER.
+//!
+//! The launcher puts JSON-encoded [`TestFilter`] into the
+//! `RUN_REAPER_INTEGRATION_TEST` environment variable. Any other value
+//! (e.g. `true`) means that all not ignored steps are run.
+
+use crate::{StepOutcome, TestStep};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Which steps are run with regard to [`TestStep::ignore`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IgnoredMode {
+    /// Ignored steps are not run.
+    #[default]
+    Skip,
+    /// All steps are run.
+    Include,
+    /// Only ignored steps are run.
+    Only,
+}
+impl IgnoredMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Skip => "skip",
+            Self::Include => "include",
+            Self::Only => "only",
+        }
+    }
+
+    fn selects(&self, step: &TestStep) -> bool {
+        match self {
+            Self::Skip => !step.ignored,
+            Self::Include => true,
+            Self::Only => step.ignored,
+        }
+    }
+}
+impl FromStr for IgnoredMode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(Self::Skip),
+            "include" => Ok(Self::Include),
+            "only" => Ok(Self::Only),
+            _ => Err(format!("unknown ignored mode: {}", s)),
+        }
+    }
+}
+
+/// Name filters, which decide which [`TestStep`]s are run.
+///
+/// Works the same way as filters of `cargo test`:
+///
+/// ```
+/// use rea_rs_test::TestFilter;
+///
+/// let filter = TestFilter::new().pattern("track").skip("slow");
+/// assert!(filter.matches_name("create track"));
+/// assert!(!filter.matches_name("create track slow"));
+/// assert!(!filter.matches_name("create item"));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TestFilter {
+    /// Step is run if its name contains any of patterns.
+    /// Empty list selects all steps.
+    pub patterns: Vec<String>,
+    /// Step is skipped if its name contains any of these patterns.
+    pub skip: Vec<String>,
+    /// Names should be equal to patterns instead of containing them.
+    pub exact: bool,
+    pub ignored: IgnoredMode,
+}
+impl TestFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add pattern, step name has to match.
+    pub fn pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.patterns.push(pattern.into());
+        self
+    }
+
+    /// Add pattern, which excludes matching steps.
+    pub fn skip(mut self, pattern: impl Into<String>) -> Self {
+        self.skip.push(pattern.into());
+        self
+    }
+
+    pub fn exact(mut self, exact: bool) -> Self {
+        self.exact = exact;
+        self
+    }
+
+    pub fn ignored(mut self, mode: IgnoredMode) -> Self {
+        self.ignored = mode;
+        self
+    }
+
+    fn name_matches(&self, name: &str, pattern: &str) -> bool {
+        match self.exact {
+            true => name == pattern,
+            false => name.contains(pattern),
+        }
+    }
+
+    /// Check name of the step against patterns, not taking
+    /// [`IgnoredMode`] into account.
+    pub fn matches_name(&self, name: &str) -> bool {
+        let selected =
+            self.patterns.is_empty() || self.patterns.iter().any(|p| self.name_matches(name, p));
+        selected && !self.skip.iter().any(|p| self.name_matches(name, p))
+    }
+
+    /// Outcome for the step, which should not be run.
+    pub(crate) fn skip_outcome(&self, step: &TestStep) -> Option<StepOutcome> {
+        if !self.matches_name(&step.name) {
+            Some(StepOutcome::FilteredOut)
+        } else if !self.ignored.selects(step) {
+            Some(StepOutcome::Ignored)
+        } else {
+            None
+        }
+    }
+
+    /// Value of `RUN_REAPER_INTEGRATION_TEST`.
+    pub(crate) fn to_env(&self) -> String {
+        serde_json::to_string(self).expect("can not serialize test filter")
+    }
+
+    /// Parses value of `RUN_REAPER_INTEGRATION_TEST`.
+    pub(crate) fn from_env(value: &str) -> Self {
+        serde_json::from_str(value).unwrap_or_default()
+    }
+}
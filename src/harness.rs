@@ -0,0 +1,168 @@
+//! Exposes every [`TestStep`](crate::TestStep) as a separate test of the
+//! libtest harness.
+//!
+//! Registered steps are discovered by launching REAPER in list mode first.
+//! Then the whole suite is run once, and every test reports the outcome of
+//! its step. So `cargo test -- step_name`, `--list`, `--ignored` and
+//! `--exact` behave the same as for usual Rust tests.
+
+use crate::{
+    integration_test::{run_in_reaper, Result},
+    report::TestReport,
+    IgnoredMode, IntegrationError, IntegrationTestConfig, PanicDetails, ReaperVersion, StepOutcome,
+    TestFilter, LIST_MODE,
+};
+use libtest_mimic::{Arguments, Failed, Trial};
+use std::sync::{Arc, OnceLock};
+
+/// Runs REAPER test steps as libtest tests and exits the process.
+///
+/// Process exits with error also if `before_all` or `after_all` hooks
+/// failed, or REAPER has not finished the suite, even if every step
+/// passed. On unsupported platforms no test is run.
+///
+/// Integration test should be declared with `harness = false`:
+///
+/// ```ignore
+/// // test/Cargo.toml
+/// [[test]]
+/// name = "integration_test"
+/// harness = false
+///
+/// // test/tests/integration_test.rs
+/// use reaper_test::{run_integration_test_harness, ReaperVersion};
+///
+/// fn main() {
+///     run_integration_test_harness(ReaperVersion::latest());
+/// }
+/// ```
+pub fn run_integration_test_harness(reaper_version: ReaperVersion) -> ! {
+    run_integration_test_harness_with(IntegrationTestConfig::new(reaper_version))
+}
+
+/// Like [`run_integration_test_harness`], but launches REAPER with the
+/// given settings.
+///
+/// Filter of the config is replaced by the one from the command line.
+pub fn run_integration_test_harness_with(config: IntegrationTestConfig) -> ! {
+    let args = Arguments::from_args();
+    let steps = match list_test_steps(&config) {
+        Ok(steps) => steps,
+        Err(err @ IntegrationError::UnsupportedPlatform) => {
+            println!("{}, skipped", err);
+            libtest_mimic::run(&args, Vec::new()).exit()
+        }
+        Err(err) => panic!("Can not discover test steps registered in REAPER: {}", err),
+    };
+    let config = config.filter(filter_from_args(&args));
+    let run: Arc<OnceLock<std::result::Result<TestReport, String>>> = Arc::new(OnceLock::new());
+    let trials = steps
+        .steps
+        .into_iter()
+        .map(|step| {
+            let run = run.clone();
+            let config = config.clone();
+            let name = step.name.clone();
+            Trial::test(step.name, move || {
+                let report = run
+                    .get_or_init(|| {
+                        match run_in_reaper(&config, config.filter.to_env()) {
+                            Ok(report) => Ok(report),
+                            // Steps of the partial report fail one by one.
+                            Err(err) => err
+                                .report()
+                                .filter(|report| !report.steps.is_empty())
+                                .cloned()
+                                .ok_or_else(|| err.to_string()),
+                        }
+                    })
+                    .as_ref()?;
+                step_result(report, &name)
+            })
+            .with_ignored_flag(step.ignored)
+        })
+        .collect();
+    let conclusion = libtest_mimic::run(&args, trials);
+    // Failures outside of steps are not seen by any test.
+    if let Some(Ok(report)) = run.get() {
+        if let Some(error) = suite_error(report) {
+            eprintln!("error: REAPER test suite failed: {}", error);
+            std::process::exit(101);
+        }
+    }
+    conclusion.exit()
+}
+
+/// Failure of `before_all` or `after_all` hooks, or the reason, why the
+/// suite has not been finished.
+fn suite_error(report: &TestReport) -> Option<String> {
+    let mut errors: Vec<String> = report
+        .setup_error
+        .iter()
+        .chain(report.teardown_error.iter())
+        .cloned()
+        .collect();
+    match (&report.aborted, report.finished) {
+        (Some(reason), _) => errors.push(format!("aborted: {}", reason)),
+        (None, false) => errors.push("REAPER exited before the suite end".into()),
+        (None, true) => (),
+    }
+    match errors.is_empty() {
+        true => None,
+        false => Some(errors.join("; ")),
+    }
+}
+
+/// Launches REAPER only to get names of registered test steps.
+pub fn list_test_steps(config: &IntegrationTestConfig) -> Result<TestReport> {
+    run_in_reaper(config, LIST_MODE.into())
+}
+
+/// Forwards filters of `cargo test` into REAPER, so only selected steps
+/// are run.
+pub fn filter_from_args(args: &Arguments) -> TestFilter {
+    let ignored = match (args.ignored, args.include_ignored) {
+        (_, true) => IgnoredMode::Include,
+        (true, false) => IgnoredMode::Only,
+        (false, false) => IgnoredMode::Skip,
+    };
+    TestFilter {
+        patterns: args.filter.iter().cloned().collect(),
+        skip: args.skip.clone(),
+        exact: args.exact,
+        ignored,
+    }
+}
+
+fn step_result(report: &TestReport, name: &str) -> std::result::Result<(), Failed> {
+    let step = report
+        .step(name)
+        .ok_or_else(|| format!("step {} has not been reported", name))?;
+    if let Some(error) = &step.teardown_error {
+        return Err(format!(
+            "{}; teardown: {}",
+            step.outcome
+                .as_ref()
+                .map_or("not finished".into(), ToString::to_string),
+            error
+        )
+        .into());
+    }
+    match &step.outcome {
+        Some(StepOutcome::Passed) | Some(StepOutcome::Ignored) => Ok(()),
+        Some(
+            outcome @ StepOutcome::Panicked(PanicDetails {
+                backtrace: Some(backtrace),
+                ..
+            }),
+        ) => Err(format!("{}\n{}", outcome, backtrace).into()),
+        Some(outcome) => Err(outcome.into()),
+        None => Err(match (&report.aborted, report.finished) {
+            (Some(reason), _) => format!("step has not been finished: {}", reason).into(),
+            (None, true) => "step has not been finished".into(),
+            (None, false) => {
+                "step has not been finished: REAPER exited before the suite end".into()
+            }
+        }),
+    }
+}
@@ -1,207 +1,454 @@
+use crate::cache::cached_reaper_executable;
+use crate::config::IntegrationTestConfig;
+use crate::display::VirtualDisplay;
+use crate::download::verify_archive;
+use crate::error::IntegrationError;
+use crate::junit::write_junit_report;
+use crate::plugin::{build_plugin, check_plugin_is_fresh, find_plugin, Workspace};
+use crate::report::{TestReport, REPORT_PATH_ENV};
+use crate::{ReaperVersion, TestFilter, INTEGRATION_TEST_ENV, LIST_MODE};
 use fs_extra::dir::CopyOptions;
-use std::error::Error;
 use std::fs::File;
 use std::io::Write;
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
-use std::time::Duration;
+use std::process::{Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
 use std::{fs, io};
 use wait_timeout::ChildExt;
 
-type Result<T> = std::result::Result<T, Box<dyn Error>>;
+pub(crate) type Result<T> = std::result::Result<T, IntegrationError>;
 
-pub enum ReaperVersion {
-    V7_71,
-    V7_73,
+/// Report of the plugin, see [`crate::report`].
+const REPORT_FILE: &str = "reaper-test-report.jsonl";
+const JUNIT_FILE: &str = "reaper-test-junit.xml";
+
+/// Runs the test suite inside REAPER and returns what the plugin
+/// reported.
+///
+/// Shortcut for [`IntegrationTestConfig::run`] with default settings.
+///
+/// # Panics
+///
+/// If REAPER can not be set up or launched, or if any test step fails.
+/// Use [`try_run_integration_test`] to handle these cases.
+pub fn run_integration_test(reaper_version: ReaperVersion) -> TestReport {
+    IntegrationTestConfig::new(reaper_version).run()
 }
-impl ReaperVersion {
-    pub fn latest() -> Self {
-        Self::V7_73
-    }
-    fn linux_download_url(&self) -> &'static str {
-        match self {
-            Self::V7_71 => "https://www.reaper.fm/files/6.x/reaper671_linux_x86_64.tar.xz",
-            Self::V7_73 => "https://www.reaper.fm/files/6.x/reaper673_linux_x86_64.tar.xz",
-        }
-    }
-    fn macos_download_url(&self) -> &'static str {
-        match self {
-            Self::V7_71 => "https://www.reaper.fm/files/6.x/reaper671_x86_64.dmg",
-            Self::V7_73 => "https://www.reaper.fm/files/6.x/reaper673_x86_64.dmg",
-        }
-    }
-    fn linux_download_path(&self) -> PathBuf {
-        match self {
-            Self::V7_71 => PathBuf::from("reaper_linux_x86_64/REAPER"),
-            Self::V7_73 => PathBuf::from("reaper_linux_x86_64/REAPER"),
-        }
+
+/// Like [`run_integration_test`], but runs only steps, matching the filter.
+///
+/// Steps, which do not match, are reported as filtered out.
+pub fn run_filtered_integration_test(
+    reaper_version: ReaperVersion,
+    filter: TestFilter,
+) -> TestReport {
+    IntegrationTestConfig::new(reaper_version)
+        .filter(filter)
+        .run()
+}
+
+/// Runs the test suite inside REAPER without panicking.
+///
+/// Returns [`IntegrationError::TestsFailed`] if any step or hook failed.
+pub fn try_run_integration_test(reaper_version: ReaperVersion) -> Result<TestReport> {
+    IntegrationTestConfig::new(reaper_version).try_run()
+}
+
+/// Like [`try_run_integration_test`], but runs only steps, matching the
+/// filter.
+pub fn try_run_filtered_integration_test(
+    reaper_version: ReaperVersion,
+    filter: TestFilter,
+) -> Result<TestReport> {
+    IntegrationTestConfig::new(reaper_version)
+        .filter(filter)
+        .try_run()
+}
+
+/// Sets up REAPER and runs the plugin in it in the given mode (see
+/// [`INTEGRATION_TEST_ENV`]).
+///
+/// Returns report even if some steps failed. If REAPER did not finish the
+/// suite, the report is carried by the error.
+pub(crate) fn run_in_reaper(config: &IntegrationTestConfig, mode: String) -> Result<TestReport> {
+    let _ = env_logger::try_init();
+    if cfg!(target_family = "windows") {
+        return Err(IntegrationError::UnsupportedPlatform);
     }
-    fn macos_download_path(&self) -> PathBuf {
-        match self {
-            Self::V7_71 => PathBuf::from("reaper_macos_x86_64"),
-            Self::V7_73 => PathBuf::from("reaper_macos_x86_64"),
-        }
+    println!("Running integration test");
+    let workspace = Workspace::resolve(config)?;
+    let executable = match config.reaper_executable_path() {
+        Some(path) => existing_reaper_executable(&path)?,
+        None => cached_reaper_executable(config, &workspace)?,
+    };
+    // Every run starts from pristine resources, so nothing leaks between
+    // runs.
+    let resource_dir_path = config.resource_dir_path(&workspace);
+    fs::create_dir_all(&resource_dir_path)?;
+    let resource_dir = tempfile::Builder::new()
+        .prefix("run-")
+        .tempdir_in(&resource_dir_path)?;
+    let resource_path = resource_dir.path().to_path_buf();
+    println!("Writing REAPER configuration...");
+    config.reaper_config.write(&resource_path)?;
+    install_plugin(config, &workspace, &resource_path)?;
+    for resource in config.resources.iter() {
+        resource.install(&resource_path, &workspace.manifest_dir)?;
     }
-    fn linux_executable_path(&self) -> PathBuf {
-        match self {
-            Self::V7_71 => PathBuf::from("reaper"),
-            Self::V7_73 => PathBuf::from("reaper"),
+    let reaper = ReaperInstance {
+        executable,
+        resource_path,
+    };
+    // Report lives in the run dir, so parallel runs do not share it.
+    let report_path = reaper.resource_path.join(REPORT_FILE);
+    let is_list = mode == LIST_MODE;
+    let result = run_integration_test_in_reaper(config, &reaper, &report_path, mode);
+    if !is_list {
+        if let Some(report) = result.as_ref().map_or_else(IntegrationError::report, Some) {
+            write_junit(report, &reaper.resource_path);
         }
+        publish_reports(&workspace.target_dir, &reaper.resource_path);
     }
-    fn macos_executable_path(&self) -> PathBuf {
-        match self {
-            Self::V7_71 => PathBuf::from("REAPER.app/Contents/MacOS/REAPER"),
-            Self::V7_73 => PathBuf::from("REAPER.app/Contents/MacOS/REAPER"),
-        }
+    match &result {
+        Ok(report) if is_list || report.is_success() => (),
+        _ => println!(
+            "REAPER resources of the failed run are kept in {:?}",
+            resource_dir.keep()
+        ),
     }
-    fn macos_install_folder(&self) -> PathBuf {
-        match self {
-            Self::V7_71 => PathBuf::from("/Volumes/REAPER_INSTALL_INTEL64/REAPER.app"),
-            Self::V7_73 => PathBuf::from("/Volumes/REAPER_INSTALL_INTEL64/REAPER.app"),
+    result
+}
+
+/// REAPER, ready to be launched.
+///
+/// Installation itself is never changed: REAPER is started by `-cfgfile`
+/// with own temporary resource directory.
+struct ReaperInstance {
+    executable: PathBuf,
+    /// Directory with `reaper.ini`, `UserPlugins` etc.
+    resource_path: PathBuf,
+}
+
+/// Finds executable of REAPER, installed on the machine.
+fn existing_reaper_executable(path: &Path) -> Result<PathBuf> {
+    let executable = match path.is_dir() {
+        false => path.to_path_buf(),
+        true if cfg!(target_os = "macos") && path.extension() == Some("app".as_ref()) => {
+            path.join("Contents/MacOS/REAPER")
         }
+        true if cfg!(target_os = "macos") => path.join("REAPER.app/Contents/MacOS/REAPER"),
+        true => path.join("reaper"),
+    };
+    match executable.is_file() {
+        true => Ok(executable),
+        false => Err(IntegrationError::ReaperNotFound(executable)),
     }
 }
 
-pub fn run_integration_test(reaper_version: ReaperVersion) {
-    env_logger::init();
-    if cfg!(target_family = "windows") {
-        println!("REAPER integration tests currently not supported on Windows");
-        return;
+/// Writes JUnit XML report into the run dir.
+fn write_junit(report: &TestReport, run_path: &Path) {
+    let path = run_path.join(JUNIT_FILE);
+    if let Err(err) = write_junit_report(report, &path) {
+        eprintln!("Can not write JUnit report to {:?}: {}", path, err);
     }
-    let target_dir_path =
-        PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap()).join("../target");
-    let reaper_download_dir_path = target_dir_path.join("reaper");
-    println!("Running integration test");
-    let result = if cfg!(target_os = "macos") {
-        run_on_macos(&reaper_version, &target_dir_path, &reaper_download_dir_path)
-    } else {
-        run_on_linux(&reaper_version, &target_dir_path, &reaper_download_dir_path)
-    };
-    result.expect("Running the integration test in REAPER failed");
 }
 
-fn run_on_linux(
-    reaper_version: &ReaperVersion,
-    target_dir_path: &Path,
-    reaper_download_dir_path: &Path,
-) -> Result<()> {
-    let reaper_home_path = setup_reaper_for_linux(reaper_version, reaper_download_dir_path)?;
-    install_plugin(&target_dir_path, &reaper_home_path)?;
-    let reaper_executable = reaper_home_path.join(reaper_version.linux_executable_path());
-    run_integration_test_in_reaper(&reaper_executable)?;
-    Ok(())
+/// Copies reports of the run into the target dir, so CI finds them at a
+/// stable path.
+///
+/// Every file is replaced at once, so parallel runs never leave a mix
+/// of their reports.
+fn publish_reports(target_dir: &Path, run_path: &Path) {
+    for name in [REPORT_FILE, JUNIT_FILE] {
+        let source = run_path.join(name);
+        if !source.exists() {
+            continue;
+        }
+        let target = target_dir.join(name);
+        let copied = fs::create_dir_all(target_dir)
+            .and_then(|_| tempfile::NamedTempFile::new_in(target_dir))
+            .and_then(|mut file| {
+                io::copy(&mut File::open(&source)?, &mut file)?;
+                file.persist(&target).map_err(|err| err.error)?;
+                Ok(())
+            });
+        match copied {
+            Ok(()) => println!("Report is written to {:?}", target),
+            Err(err) => eprintln!("Can not copy {:?} to {:?}: {}", source, target, err),
+        }
+    }
 }
 
-fn run_on_macos(
-    reaper_version: &ReaperVersion,
-    target_dir_path: &Path,
-    reaper_download_dir_path: &Path,
+fn install_plugin(
+    config: &IntegrationTestConfig,
+    workspace: &Workspace,
+    resource_path: &Path,
 ) -> Result<()> {
-    let reaper_home_path = setup_reaper_for_macos(reaper_version, reaper_download_dir_path)?;
-    install_plugin(&target_dir_path, &reaper_home_path)?;
-    let reaper_executable = reaper_home_path.join(reaper_version.macos_executable_path());
-    run_integration_test_in_reaper(&reaper_executable)?;
+    if config.build_plugin {
+        build_plugin(config, workspace)?;
+    }
+    let plugin = find_plugin(config, workspace)?;
+    if !config.build_plugin {
+        check_plugin_is_fresh(config, workspace, &plugin)?;
+    }
+    let user_plugins_path = resource_path.join("UserPlugins");
+    let target_path = user_plugins_path.join(&plugin.install_name);
+    fs::create_dir_all(&user_plugins_path)?;
+    println!(
+        "Copying plug-in {:?} to {:?}...",
+        &plugin.path, &target_path
+    );
+    fs::copy(&plugin.path, &target_path)?;
     Ok(())
 }
 
-fn install_plugin(target_dir_path: &Path, reaper_home_path: &Path) -> Result<()> {
-    let extension = if cfg!(target_os = "macos") {
-        "dylib"
-    } else {
-        "so"
+fn run_integration_test_in_reaper(
+    config: &IntegrationTestConfig,
+    reaper: &ReaperInstance,
+    report_path: &Path,
+    mode: String,
+) -> Result<TestReport> {
+    println!(
+        "Starting REAPER ({:?}) with resources in {:?}...",
+        &reaper.executable, &reaper.resource_path
+    );
+    let display = match config.virtual_display {
+        true => VirtualDisplay::start_if_needed()?,
+        false => None,
     };
-    let source_path = target_dir_path
-        .join("debug")
-        .join(format!("libreaper_test_extension_plugin.{}", extension));
-    let target_path = reaper_home_path
-        .join("UserPlugins")
-        .join(format!("reaper_test_extension_plugin.{}", extension));
-    fs::create_dir_all(target_path.parent().ok_or("no parent")?)?;
-    println!("Copying plug-in to {:?}...", &target_path);
-    fs::copy(&source_path, &target_path)?;
-    Ok(())
-}
-
-fn run_integration_test_in_reaper(reaper_executable: &Path) -> Result<()> {
-    println!("Starting REAPER ({:?})...", &reaper_executable);
-    let mut child = Command::new(reaper_executable)
-        .env("RUN_REAPER_INTEGRATION_TEST", "true")
-        .env("RUST_LOG", "debug")
+    let mut command = Command::new(&reaper.executable);
+    if let Some(display) = &display {
+        command.env("DISPLAY", display.name());
+    }
+    let mut child = command
+        .arg("-cfgfile")
+        .arg(reaper.resource_path.join("reaper.ini"))
+        .envs(config.env.iter().map(|(key, value)| (key, value)))
+        .env(INTEGRATION_TEST_ENV, mode)
+        .env("RUST_LOG", config.log_level.to_string().to_lowercase())
+        .env(REPORT_PATH_ENV, report_path)
         .arg("-newinst")
         .arg("-new")
+        .args(config.reaper_config.launch_args())
+        .args(&config.args)
         // .arg("-splashlog")
         // .arg("splash.log")
-        .spawn()?;
-    let exit_status = child.wait_timeout(Duration::from_secs(120))?;
-    let exit_status = match exit_status {
-        None => {
+        .spawn()
+        .map_err(IntegrationError::LaunchFailed)?;
+    let exit_status = match wait_for_reaper(&mut child, report_path, config.timeout)? {
+        ReaperExit::Exited(status) => status,
+        ReaperExit::HungOnShutdown => {
+            child.kill()?;
+            child.wait()?;
+            log::warn!(
+                "REAPER didn't quit in {:?} after the suite end, it has been killed",
+                SHUTDOWN_GRACE
+            );
+            let report = read_report(report_path)?;
+            println!("{}", report);
+            return Ok(report);
+        }
+        ReaperExit::Hung { reason, elapsed } => {
             child.kill()?;
-            return Err(
-                "REAPER didn't exit in time (maybe integration test has not started at all)",
-            )?;
+            child.wait()?;
+            let mut report = read_report(report_path)?;
+            report.time_out_running_step(elapsed);
+            report.abort(reason);
+            println!("{}", report);
+            return Err(IntegrationError::ReaperTimedOut(Box::new(report)));
         }
-        Some(s) => s,
     };
-    if exit_status.success() {
-        return Ok(());
+    // Result is judged only by the report: exit code of REAPER does not
+    // tell anything about the tests.
+    let mut report = read_report(report_path)?;
+    if !report.finished {
+        report.abort(format!(
+            "REAPER exited ({}) before the suite end",
+            exit_status
+        ));
+        println!("{}", report);
+        return Err(IntegrationError::ReaperCrashed {
+            signal: exit_signal(&exit_status),
+            code: exit_status.code(),
+            report: Box::new(report),
+        });
+    }
+    println!("{}", report);
+    Ok(report)
+}
+
+#[cfg(unix)]
+fn exit_signal(status: &ExitStatus) -> Option<i32> {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+}
+
+#[cfg(not(unix))]
+fn exit_signal(_status: &ExitStatus) -> Option<i32> {
+    None
+}
+
+fn read_report(report_path: &Path) -> Result<TestReport> {
+    match report_path.exists() {
+        true => Ok(TestReport::read(report_path)?),
+        false => Ok(TestReport::default()),
+    }
+}
+
+/// Time, given to REAPER to quit after the suite end.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(10);
+
+/// How waiting for REAPER ended.
+enum ReaperExit {
+    Exited(ExitStatus),
+    /// Suite has been finished, but REAPER didn't quit, e.g. because of
+    /// a prompt or a hanging destroy hook of a plugin.
+    HungOnShutdown,
+    /// Suite or its step didn't finish in time.
+    Hung {
+        reason: String,
+        /// Time, the running step took.
+        elapsed: Duration,
+    },
+}
+
+/// Waits for REAPER to exit, watching the report for steps, which exceed
+/// their timeout.
+///
+/// Sync step can not be interrupted inside REAPER, so if it hangs, the
+/// only way to stop it is to kill REAPER.
+fn wait_for_reaper(
+    child: &mut std::process::Child,
+    report_path: &Path,
+    timeout: Duration,
+) -> Result<ReaperExit> {
+    /// Time, given to REAPER to stop the step by itself.
+    const GRACE: Duration = Duration::from_secs(5);
+    let started = Instant::now();
+    let mut running: Option<(String, Instant)> = None;
+    let mut finished: Option<Instant> = None;
+    loop {
+        if let Some(status) = child.wait_timeout(Duration::from_millis(500))? {
+            return Ok(ReaperExit::Exited(status));
+        }
+        let report = read_report(report_path)?;
+        // Result is already known, only shutdown is awaited.
+        if report.finished {
+            let since = *finished.get_or_insert_with(Instant::now);
+            if since.elapsed() > SHUTDOWN_GRACE {
+                return Ok(ReaperExit::HungOnShutdown);
+            }
+            continue;
+        }
+        let step = report.running_step();
+        if running.as_ref().map(|(name, _)| name) != step.map(|s| &s.name) {
+            running = step.map(|s| (s.name.clone(), Instant::now()));
+        }
+        let step_elapsed = running
+            .as_ref()
+            .map_or(Duration::ZERO, |(_, since)| since.elapsed());
+        if let Some(limit) = step.and_then(|s| s.timeout) {
+            if step_elapsed > limit + GRACE {
+                let name = &step.expect("checked above").name;
+                return Ok(ReaperExit::Hung {
+                    reason: format!("step {} hung", name),
+                    elapsed: step_elapsed,
+                });
+            }
+        }
+        if started.elapsed() > timeout {
+            let reason = match step {
+                Some(step) => format!("REAPER didn't exit in time, step {} hung", step.name),
+                None => "REAPER didn't exit in time".to_string(),
+            };
+            return Ok(ReaperExit::Hung {
+                reason,
+                elapsed: step_elapsed,
+            });
+        }
+    }
+}
+
+/// Lists every failed step with its error.
+pub(crate) fn failure_message(report: &TestReport) -> String {
+    let mut message = String::from("Integration test failed:");
+    if let Some(reason) = &report.aborted {
+        message.push_str(&format!("\n    aborted: {}", reason));
+    }
+    for error in report.setup_error.iter() {
+        message.push_str(&format!("\n    {}", error));
+    }
+    for step in report.failures() {
+        match &step.outcome {
+            Some(outcome) => message.push_str(&format!("\n    {}: {}", step.name, outcome)),
+            None => message.push_str(&format!("\n    {}: not finished", step.name)),
+        }
+        if let Some(error) = &step.teardown_error {
+            message.push_str(&format!("\n    {}: teardown: {}", step.name, error));
+        }
     }
-    let exit_code = exit_status.code().unwrap_or(101);
-    if exit_code == 172 {
-        Err("Integration test failed")?
-    } else if exit_code == 101 {
-        println!("Exited with code 101, which is, probably, normal for linux");
-        Ok(())
-    } else {
-        Err(
-            "REAPER exited unsuccessfully but neither because of signal nor because of failed \
-            integration test",
-        )?
+    for error in report.teardown_error.iter() {
+        message.push_str(&format!("\n    {}", error));
     }
+    message
 }
 
+/// Downloads REAPER archive into `archive_dir_path` and unpacks it into
+/// `install_path`.
+///
 /// Returns path of REAPER home
-fn setup_reaper_for_linux(
-    reaper_version: &ReaperVersion,
-    reaper_download_dir_path: &Path,
+pub(crate) fn setup_reaper_for_linux(
+    config: &IntegrationTestConfig,
+    archive_dir_path: &Path,
+    install_path: &Path,
 ) -> Result<PathBuf> {
-    let reaper_home_path = reaper_download_dir_path.join(reaper_version.linux_download_path());
-    let reaper_check_path = reaper_download_dir_path.join("/reaper");
-    if reaper_check_path.exists() {
-        return Ok(reaper_home_path);
-    }
-    let reaper_tarball_path = reaper_download_dir_path.join("reaper-linux.tar.xz");
+    let reaper_version = &config.reaper_version;
+    let reaper_home_path = install_path.join(reaper_version.linux_download_path());
+    let reaper_tarball_path = archive_dir_path.join(reaper_version.linux_archive_name());
     if !reaper_tarball_path.exists() {
         println!("Downloading REAPER to ({:?})...", &reaper_tarball_path);
-        download(reaper_version.linux_download_url(), &reaper_tarball_path)?;
+        config.download_source_or_default().fetch(
+            reaper_version,
+            &reaper_version.linux_archive_name(),
+            &reaper_tarball_path,
+        )?;
     }
+    verify_archive(config, &reaper_tarball_path)?;
     println!("Unpacking REAPER tarball...");
-    unpack_tar_xz(&reaper_tarball_path, &reaper_download_dir_path)?;
-    write_reaper_config(&reaper_home_path)?;
+    unpack_tar_xz(&reaper_tarball_path, install_path)?;
     println!("REAPER home directory is {:?}", &reaper_home_path);
     Ok(reaper_home_path)
 }
 
+/// Downloads REAPER dmg into `archive_dir_path` and copies the app from it
+/// into `install_path`.
+///
 /// Returns path of REAPER home
-fn setup_reaper_for_macos(
-    reaper_version: &ReaperVersion,
-    reaper_download_dir_path: &Path,
+pub(crate) fn setup_reaper_for_macos(
+    config: &IntegrationTestConfig,
+    archive_dir_path: &Path,
+    install_path: &Path,
 ) -> Result<PathBuf> {
-    let reaper_home_path = reaper_download_dir_path.join(reaper_version.macos_download_path());
-    if reaper_home_path.exists() {
-        return Ok(reaper_home_path);
-    }
-    let reaper_dmg_path = reaper_download_dir_path.join("reaper-macos.dmg");
+    let reaper_version = &config.reaper_version;
+    let reaper_home_path = install_path.join(reaper_version.macos_download_path());
+    let reaper_dmg_path = archive_dir_path.join(reaper_version.macos_archive_name());
     if !reaper_dmg_path.exists() {
         println!("Downloading REAPER to ({:?})...", &reaper_dmg_path);
-        download(reaper_version.macos_download_url(), &reaper_dmg_path)?;
+        config.download_source_or_default().fetch(
+            reaper_version,
+            &reaper_version.macos_archive_name(),
+            &reaper_dmg_path,
+        )?;
     }
+    verify_archive(config, &reaper_dmg_path)?;
     println!("Unpacking REAPER dmg...");
-    mount_dmg(&reaper_dmg_path)?;
+    let mounted = MountedDmg::attach(&reaper_dmg_path)?;
+    let app_path = mounted.path().join(reaper_version.macos_app_in_dmg());
     println!("Copying from mount...");
     fs::create_dir_all(&reaper_home_path)?;
 
     fs_extra::dir::copy(
-        reaper_version.macos_install_folder(),
+        &app_path,
         &reaper_home_path,
         &CopyOptions {
             overwrite: false,
@@ -211,70 +458,89 @@ fn setup_reaper_for_macos(
             depth: 0,
             ..Default::default()
         },
-    )?;
-    write_reaper_config(&reaper_home_path)?;
+    )
+    .map_err(|err| IntegrationError::UnpackFailed {
+        path: app_path.clone(),
+        source: err.into(),
+    })?;
+    drop(mounted);
     remove_rewire_plugin_macos_bundle(&reaper_home_path)?;
     println!("REAPER home directory is {:?}", &reaper_home_path);
     Ok(reaper_home_path)
 }
 
-fn write_reaper_config(reaper_home_path: &Path) -> Result<()> {
-    println!("Writing REAPER configuration...");
-    let content = r#"
-[audioconfig]
-; For dummy audio on Windows
-mode=4
-
-[REAPER]
-; For dummy audio on Linux
-linux_audio_mode=2
-; For <none> audio on macOS
-coreaudiobs=512
-coreaudioindevnew=<none>
-coreaudiooutdevnew=<none>
-"#;
-    fs::write(reaper_home_path.join("reaper.ini"), content)?;
-    Ok(())
-}
-
 fn remove_rewire_plugin_macos_bundle(reaper_home_path: &Path) -> Result<()> {
     println!("Removing Rewire plug-in (because it makes REAPER get stuck on headless macOS)...");
-    fs::remove_dir_all(reaper_home_path.join("REAPER.app/Contents/Plugins/ReWire.bundle"))?;
-    Ok(())
-}
-
-fn download(url: &str, dest_file_path: &Path) -> Result<()> {
-    let mut response = reqwest::blocking::get(url)?;
-    fs::create_dir_all(
-        dest_file_path
-            .parent()
-            .ok_or("download destination path must be absolute")?,
-    )?;
-    let mut dest_file = fs::File::create(&dest_file_path)?;
-    io::copy(&mut response, &mut dest_file)?;
-    Ok(())
+    // Newer builds may not ship it at all.
+    match fs::remove_dir_all(reaper_home_path.join("REAPER.app/Contents/Plugins/ReWire.bundle")) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
 }
 
 fn unpack_tar_xz(file_path: &Path, dest_dir_path: &Path) -> Result<()> {
-    let tar_xz = File::open(file_path)?;
+    let failed = |err: io::Error| IntegrationError::UnpackFailed {
+        path: file_path.to_path_buf(),
+        source: err.into(),
+    };
+    let tar_xz = File::open(file_path).map_err(failed)?;
     let tar = xz2::read::XzDecoder::new(tar_xz);
     let mut archive = tar::Archive::new(tar);
-    archive.unpack(dest_dir_path)?;
+    archive.unpack(dest_dir_path).map_err(failed)?;
     Ok(())
 }
 
-fn mount_dmg(file_path: &Path) -> Result<()> {
-    let mut child = Command::new("hdiutil")
-        .arg("attach")
-        .arg(file_path)
-        .stdin(Stdio::piped())
-        .spawn()?;
-    let stdin = child.stdin.as_mut().ok_or("Failed to open stdin")?;
-    // Get rid of displayed license by simulating q and y key presses
-    stdin.write_all("q\nq\ny\ny\ny\ny\n".as_bytes())?;
-    let status = child.wait()?;
-    if !status.success() {
-        return Err("mount not successful".into());
+/// Dmg, mounted at a private mount point, so it never clashes with dmgs
+/// of other versions. Detached on drop.
+struct MountedDmg {
+    mount_point: tempfile::TempDir,
+}
+impl MountedDmg {
+    fn attach(file_path: &Path) -> Result<Self> {
+        let failed =
+            |source: Box<dyn std::error::Error + Send + Sync>| IntegrationError::UnpackFailed {
+                path: file_path.to_path_buf(),
+                source,
+            };
+        let mount_point = tempfile::Builder::new().prefix("reaper-dmg-").tempdir()?;
+        let mut child = Command::new("hdiutil")
+            .arg("attach")
+            .arg("-nobrowse")
+            .arg("-mountpoint")
+            .arg(mount_point.path())
+            .arg(file_path)
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|err| failed(err.into()))?;
+        let stdin = child
+            .stdin
+            .as_mut()
+            .ok_or_else(|| failed("Failed to open stdin".into()))?;
+        // Get rid of displayed license by simulating q and y key presses
+        stdin.write_all("q\nq\ny\ny\ny\ny\n".as_bytes())?;
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(failed("mount not successful".into()));
+        }
+        Ok(Self { mount_point })
+    }
+
+    fn path(&self) -> &Path {
+        self.mount_point.path()
+    }
+}
+impl Drop for MountedDmg {
+    fn drop(&mut self) {
+        let detach = |force: bool| {
+            let mut command = Command::new("hdiutil");
+            command.arg("detach").arg(self.path()).arg("-quiet");
+            if force {
+                command.arg("-force");
+            }
+            command.status().is_ok_and(|status| status.success())
+        };
+        if !detach(false) && !detach(true) {
+            eprintln!("Can not detach REAPER dmg mounted at {:?}", self.path());
+        }
     }
-    Ok(())
 }
@@ -0,0 +1,256 @@
+//! JUnit XML output of the integration test run, for CI dashboards.
+
+use crate::{StepOutcome, StepReport, TestReport};
+use std::{fmt::Write, fs, io, path::Path};
+
+/// Writes report in JUnit XML format, one `testcase` per test step.
+///
+/// Version of REAPER, which has run the suite, is recorded as
+/// `reaper.version` property of the suite.
+pub fn write_junit_report(report: &TestReport, path: &Path) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, render_junit_report(report))
+}
+
+/// Renders report in JUnit XML format.
+pub fn render_junit_report(report: &TestReport) -> String {
+    let suite_name = std::env::var("CARGO_PKG_NAME").unwrap_or_else(|_| "reaper-test".into());
+    let count =
+        |predicate: fn(&StepReport) -> bool| report.steps.iter().filter(|s| predicate(s)).count();
+    let failures = count(|s| {
+        matches!(
+            s.outcome,
+            Some(StepOutcome::Failed(_)) | Some(StepOutcome::TimedOut(_))
+        )
+    });
+    let errors = count(|s| {
+        matches!(s.outcome, Some(StepOutcome::Panicked(_)) | None) || s.teardown_error.is_some()
+    });
+    let skipped = count(|s| {
+        matches!(
+            s.outcome,
+            Some(StepOutcome::Ignored) | Some(StepOutcome::FilteredOut)
+        )
+    });
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    // Writing into String never fails.
+    let _ = writeln!(
+        xml,
+        "<testsuites tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\" time=\"{time:.3}\">\n  \
+         <testsuite name=\"{name}\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\" \
+         skipped=\"{skipped}\" time=\"{time:.3}\">",
+        name = escape(&suite_name),
+        tests = report.steps.len(),
+        time = report.duration.as_secs_f64(),
+    );
+    if let Some(version) = &report.reaper_version {
+        let _ = writeln!(
+            xml,
+            "    <properties>\n      \
+             <property name=\"reaper.version\" value=\"{}\"/>\n    \
+             </properties>",
+            escape(version)
+        );
+    }
+    for step in report.steps.iter() {
+        write_testcase(&mut xml, &suite_name, step);
+    }
+    for error in report
+        .setup_error
+        .iter()
+        .chain(report.teardown_error.iter())
+    {
+        let _ = writeln!(xml, "    <system-err>{}</system-err>", escape(error));
+    }
+    if let Some(reason) = &report.aborted {
+        let _ = writeln!(xml, "    <system-err>{}</system-err>", escape(reason));
+    } else if !report.finished {
+        let _ = writeln!(
+            xml,
+            "    <system-err>REAPER exited before the end of the suite</system-err>"
+        );
+    }
+    if !report.logs.is_empty() {
+        let _ = writeln!(
+            xml,
+            "    <system-out>{}</system-out>",
+            escape(&report.logs.join("\n"))
+        );
+    }
+    xml.push_str("  </testsuite>\n</testsuites>\n");
+    xml
+}
+
+fn write_testcase(xml: &mut String, class_name: &str, step: &StepReport) {
+    let _ = writeln!(
+        xml,
+        "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\">",
+        escape(&step.name),
+        escape(class_name),
+        step.duration.as_secs_f64()
+    );
+    match &step.outcome {
+        Some(StepOutcome::Passed) => (),
+        Some(StepOutcome::Failed(message)) => {
+            let _ = writeln!(
+                xml,
+                "      <failure type=\"failed\" message=\"{0}\">{0}</failure>",
+                escape(message)
+            );
+        }
+        Some(StepOutcome::TimedOut(limit)) => {
+            let _ = writeln!(
+                xml,
+                "      <failure type=\"timeout\" message=\"exceeded {:?}\"/>",
+                limit
+            );
+        }
+        Some(StepOutcome::Panicked(details)) => {
+            let mut body = details.to_string();
+            if let Some(backtrace) = &details.backtrace {
+                body.push('\n');
+                body.push_str(backtrace);
+            }
+            let _ = writeln!(
+                xml,
+                "      <error type=\"panic\" message=\"{}\">{}</error>",
+                escape(&details.to_string()),
+                escape(&body)
+            );
+        }
+        Some(StepOutcome::Ignored) => xml.push_str("      <skipped message=\"ignored\"/>\n"),
+        Some(StepOutcome::FilteredOut) => {
+            xml.push_str("      <skipped message=\"filtered out\"/>\n")
+        }
+        None => xml.push_str(
+            "      <error type=\"not finished\" message=\"step has not been finished\"/>\n",
+        ),
+    }
+    if let Some(error) = &step.teardown_error {
+        let _ = writeln!(
+            xml,
+            "      <error type=\"teardown\" message=\"{0}\">{0}</error>",
+            escape(error)
+        );
+    }
+    if !step.logs.is_empty() {
+        let _ = writeln!(
+            xml,
+            "      <system-out>{}</system-out>",
+            escape(&step.logs.join("\n"))
+        );
+    }
+    xml.push_str("    </testcase>\n");
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' | '\r' | '\t' => escaped.push(c),
+            // Not allowed in XML 1.0 at all.
+            c if (c as u32) < 0x20 => (),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PanicDetails;
+    use std::time::Duration;
+
+    fn step(name: &str, outcome: Option<StepOutcome>) -> StepReport {
+        StepReport {
+            name: name.to_string(),
+            ignored: false,
+            started: outcome.is_some(),
+            timeout: None,
+            outcome,
+            teardown_error: None,
+            duration: Duration::from_millis(250),
+            logs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn escape_markup_and_control_characters() {
+        assert_eq!(
+            escape("a<b> & \"c\" 'd'"),
+            "a&lt;b&gt; &amp; &quot;c&quot; &apos;d&apos;"
+        );
+        assert_eq!(escape("line\n\tnext\r"), "line\n\tnext\r");
+        assert_eq!(escape("bell\u{7}esc\u{1b}nul\u{0}"), "bellescnul");
+        assert_eq!(escape("ünïcödé"), "ünïcödé");
+    }
+
+    #[test]
+    fn counts_failures_errors_and_skipped() {
+        let mut teardown = step("teardown", Some(StepOutcome::Passed));
+        teardown.teardown_error = Some("after_each hook FAILED".into());
+        let report = TestReport {
+            steps: vec![
+                step("passed", Some(StepOutcome::Passed)),
+                step("failed", Some(StepOutcome::Failed("wrong".into()))),
+                step(
+                    "timed_out",
+                    Some(StepOutcome::TimedOut(Duration::from_secs(1))),
+                ),
+                step(
+                    "panicked",
+                    Some(StepOutcome::Panicked(PanicDetails {
+                        message: "boom".into(),
+                        location: Some("src/lib.rs:1:1".into()),
+                        backtrace: None,
+                    })),
+                ),
+                step("unfinished", None),
+                step("ignored", Some(StepOutcome::Ignored)),
+                step("filtered", Some(StepOutcome::FilteredOut)),
+                teardown,
+            ],
+            finished: true,
+            duration: Duration::from_secs(2),
+            reaper_version: Some("7.15/linux-x86_64".into()),
+            ..TestReport::default()
+        };
+        let xml = render_junit_report(&report);
+        assert!(
+            xml.contains("tests=\"8\" failures=\"2\" errors=\"3\" skipped=\"2\" time=\"2.000\">")
+        );
+        assert!(xml.contains("<property name=\"reaper.version\" value=\"7.15/linux-x86_64\"/>"));
+        assert_eq!(xml.matches("<testcase ").count(), 8);
+        assert_eq!(xml.matches("<skipped ").count(), 2);
+        assert!(xml.contains("<failure type=\"failed\" message=\"wrong\">wrong</failure>"));
+        assert!(xml.contains("<error type=\"teardown\""));
+        assert!(!xml.contains("<system-err>"));
+    }
+
+    #[test]
+    fn escapes_messages_and_reports_suite_errors() {
+        let report = TestReport {
+            steps: vec![step(
+                "compare",
+                Some(StepOutcome::Failed("1 < 2 && \"x\"".into())),
+            )],
+            teardown_error: Some("after_all hook FAILED: <oops>".into()),
+            ..TestReport::default()
+        };
+        let xml = render_junit_report(&report);
+        assert!(!xml.contains("<properties>"));
+        assert!(xml.contains(
+            "message=\"1 &lt; 2 &amp;&amp; &quot;x&quot;\">1 &lt; 2 &amp;&amp; &quot;x&quot;</failure>"
+        ));
+        assert!(xml.contains("<system-err>after_all hook FAILED: &lt;oops&gt;</system-err>"));
+        assert!(xml.contains("<system-err>REAPER exited before the end of the suite</system-err>"));
+    }
+}
@@ -59,6 +59,24 @@
 //! }
 //! ```
 //!
+//! Alternatively, every test step can be reported to cargo as a separate
+//! test, so they can be listed and filtered as usual tests. Add to
+//! `test/Cargo.toml`:
+//! ```ignore
+//! [[test]]
+//! name = "integration_test"
+//! harness = false
+//! ```
+//!
+//! and replace contents of `test/tests/integration_test.rs` with:
+//! ```ignore
+//! use reaper_test::{run_integration_test_harness, ReaperVersion};
+//!
+//! fn main() {
+//!     run_integration_test_harness(ReaperVersion::latest());
+//! }
+//! ```
+//!
 //! `test/src/lib.rs` is the file your integration tests are placed in.
 //! ```ignore
 //! use rea_rs::{PluginContext, Reaper};
@@ -82,24 +100,84 @@
 //! ```
 //!
 //! to run integration tests, go to the test folder and type:
-//! `cargo build --workspace; cargo test`
+//! `cargo test`
+//!
+//! The plugin is built by the launcher itself before REAPER is started.
 //!
 
+use asynchronous::AsyncStep;
 use rea_rs::{PluginContext, Reaper, Timer};
 use rea_rs_low::register_plugin_destroy_hook;
-use std::{error::Error, fmt::Debug, panic, process};
+use report::{ReportLogger, StepInfo};
+use runner::SuiteRun;
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fmt::{Debug, Display},
+    future::Future,
+    rc::Rc,
+    time::Duration,
+};
 
+pub mod asynchronous;
+mod cache;
+pub use cache::REAPER_CACHE_ENV;
+pub mod config;
+pub use config::{IntegrationTestConfig, REAPER_EXECUTABLE_ENV};
+pub mod deferred;
+pub use deferred::*;
+mod display;
+pub mod download;
+pub use download::{DownloadSource, REAPER_MIRROR_ENV};
+pub mod error;
+pub use error::IntegrationError;
+pub mod filter;
+pub use filter::{IgnoredMode, TestFilter};
+pub mod harness;
+pub use harness::*;
 pub mod integration_test;
-pub use integration_test::*;
+pub use integration_test::{
+    run_filtered_integration_test, run_integration_test, try_run_filtered_integration_test,
+    try_run_integration_test,
+};
+pub mod junit;
+pub mod panics;
+pub use panics::PanicDetails;
+mod plugin;
+mod project;
+pub mod reaper_config;
+pub use reaper_config::ReaperConfig;
+pub mod report;
+pub use report::{StepReport, TestReport};
+pub mod resources;
+pub use resources::{Resource, ResourceFolder};
+mod runner;
+pub mod version;
+pub use version::{ParseVersionError, ReaperVersion};
 
 static mut INSTANCE: Option<ReaperTest> = None;
 
+/// Set by the launcher to tell the plugin it runs under integration test.
+///
+/// Value `list` asks only to report the registered steps without
+/// running them. Otherwise, it may hold [`TestFilter`].
+pub(crate) const INTEGRATION_TEST_ENV: &str = "RUN_REAPER_INTEGRATION_TEST";
+pub(crate) const LIST_MODE: &str = "list";
+
 pub type TestStepResult = Result<(), Box<dyn Error>>;
 pub type TestCallback = dyn Fn(&'static mut Reaper) -> TestStepResult;
 
+enum Operation {
+    Sync(Box<TestCallback>),
+    /// Makes fresh state of the step every time it is started.
+    Deferred(Box<dyn Fn() -> Box<dyn DeferredStep>>),
+}
+
 pub struct TestStep {
     name: String,
-    operation: Box<TestCallback>,
+    operation: Operation,
+    ignored: bool,
+    timeout: Option<Duration>,
 }
 impl TestStep {
     pub fn new(
@@ -108,7 +186,70 @@ impl TestStep {
     ) -> Self {
         Self {
             name: name.into(),
-            operation: Box::new(operation),
+            operation: Operation::Sync(Box::new(operation)),
+            ignored: false,
+            timeout: None,
+        }
+    }
+
+    /// Step, which is polled on every tick of REAPER main loop until it is
+    /// ready.
+    ///
+    /// `make` is called every time the step is started, so it should
+    /// return a step in its initial state. See [`deferred`] module.
+    pub fn deferred<S: DeferredStep + 'static>(
+        name: impl Into<String>,
+        make: impl Fn() -> S + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            operation: Operation::Deferred(Box::new(move || -> Box<dyn DeferredStep> {
+                Box::new(make())
+            })),
+            ignored: false,
+            timeout: None,
+        }
+    }
+
+    /// Step, made of `async` function, which is driven on REAPER main
+    /// thread. See [`asynchronous`] module.
+    pub fn asynchronous<Fut>(
+        name: impl Into<String>,
+        operation: impl Fn(&'static mut Reaper) -> Fut + 'static,
+    ) -> Self
+    where
+        Fut: Future<Output = TestStepResult> + 'static,
+    {
+        let operation = Rc::new(operation);
+        Self::deferred(name, move || {
+            let operation = operation.clone();
+            AsyncStep::new(move |reaper| operation(reaper))
+        })
+    }
+
+    /// Mark step as ignored, like `#[ignore]` does for usual tests.
+    ///
+    /// Such step is run only if `--ignored` or `--include-ignored` is passed.
+    pub fn ignore(mut self) -> Self {
+        self.ignored = true;
+        self
+    }
+
+    /// Fail the step if it runs longer than `timeout`.
+    ///
+    /// Overrides [`ReaperTest::default_timeout`]. Deferred and async steps
+    /// are stopped as soon as the limit is over. Sync step can not be
+    /// interrupted, so it is marked as timed out after it returns, or by
+    /// the launcher, if it hangs.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    fn info(&self) -> StepInfo {
+        StepInfo {
+            name: self.name.clone(),
+            ignored: self.ignored,
         }
     }
 }
@@ -118,16 +259,69 @@ impl Debug for TestStep {
     }
 }
 
+/// How a single [`TestStep`] ended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", content = "message", rename_all = "snake_case")]
+pub enum StepOutcome {
+    Passed,
+    /// Step returned `Err` with the given message.
+    Failed(String),
+    /// Step panicked.
+    Panicked(PanicDetails),
+    /// Step has not been finished in the given time.
+    TimedOut(Duration),
+    /// Step was not run, because it is marked by [`TestStep::ignore`].
+    Ignored,
+    /// Step was not run, because it does not match [`TestFilter`].
+    FilteredOut,
+}
+impl StepOutcome {
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            Self::Failed(_) | Self::Panicked(_) | Self::TimedOut(_)
+        )
+    }
+}
+impl Display for StepOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Passed => write!(f, "ok"),
+            Self::Failed(reason) => write!(f, "FAILED: {}", reason),
+            Self::Panicked(details) => write!(f, "PANICKED: {}", details),
+            Self::TimedOut(limit) => write!(f, "TIMED OUT: exceeded {:?}", limit),
+            Self::Ignored => write!(f, "ignored"),
+            Self::FilteredOut => write!(f, "filtered out"),
+        }
+    }
+}
+
+/// Result of a single [`TestStep`], identified by its name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepResult {
+    pub name: String,
+    pub outcome: StepOutcome,
+    pub duration: Duration,
+    /// Failure of `after_each` hooks, reported apart from the step outcome.
+    pub teardown_error: Option<String>,
+}
+
 fn test(_flag: i32) -> Result<(), Box<dyn Error>> {
-    ReaperTest::get_mut().test();
+    let r_test = ReaperTest::get_mut();
+    if !r_test.is_running() {
+        r_test.start();
+        r_test.reaper.register_timer(Box::new(IntegrationTimer {}));
+    }
     Ok(())
 }
 
+/// Drives the suite, one step of the runner per tick.
 struct IntegrationTimer {}
 impl Timer for IntegrationTimer {
     fn run(&mut self) -> Result<(), Box<dyn Error>> {
-        test(0)?;
-        self.stop();
+        if ReaperTest::get_mut().tick() {
+            self.stop();
+        }
         Ok(())
     }
 
@@ -140,6 +334,15 @@ pub struct ReaperTest {
     reaper: Reaper,
     steps: Vec<TestStep>,
     is_integration_test: bool,
+    list_only: bool,
+    filter: TestFilter,
+    before_all: Vec<Box<TestCallback>>,
+    after_all: Vec<Box<TestCallback>>,
+    before_each: Vec<Box<TestCallback>>,
+    after_each: Vec<Box<TestCallback>>,
+    isolate_projects: bool,
+    default_timeout: Option<Duration>,
+    run: Option<SuiteRun>,
 }
 impl ReaperTest {
     fn make_available_globally(r_test: ReaperTest) {
@@ -153,10 +356,23 @@ impl ReaperTest {
     }
     pub fn setup(context: PluginContext, action_name: &'static str) -> &'static mut Self {
         let reaper = Reaper::load(context);
+        let mode = std::env::var(INTEGRATION_TEST_ENV).ok();
         let mut instance = Self {
             reaper,
             steps: Vec::new(),
-            is_integration_test: std::env::var("RUN_REAPER_INTEGRATION_TEST").is_ok(),
+            is_integration_test: mode.is_some(),
+            list_only: mode.as_deref() == Some(LIST_MODE),
+            filter: mode
+                .as_deref()
+                .map(TestFilter::from_env)
+                .unwrap_or_default(),
+            before_all: Vec::new(),
+            after_all: Vec::new(),
+            before_each: Vec::new(),
+            after_each: Vec::new(),
+            isolate_projects: false,
+            default_timeout: None,
+            run: None,
         };
         let integration = instance.is_integration_test;
         instance
@@ -166,6 +382,7 @@ impl ReaperTest {
         Self::make_available_globally(instance);
         let obj = ReaperTest::get_mut();
         if integration {
+            ReportLogger::install();
             obj.reaper.register_timer(Box::new(IntegrationTimer {}))
         }
         ReaperTest::get_mut()
@@ -181,67 +398,63 @@ impl ReaperTest {
     /// [`make_available_globally()`]: fn.make_available_globally.html
     pub fn get() -> &'static ReaperTest {
         unsafe {
-            INSTANCE
+            (*std::ptr::addr_of!(INSTANCE))
                 .as_ref()
                 .expect("call `load(context)` before using `get()`")
         }
     }
     pub fn get_mut() -> &'static mut ReaperTest {
         unsafe {
-            INSTANCE
+            (*std::ptr::addr_of_mut!(INSTANCE))
                 .as_mut()
                 .expect("call `load(context)` before using `get()`")
         }
     }
 
-    fn test(&mut self) {
-        println!("# Testing reaper-rs\n");
-        let result = panic::catch_unwind(|| -> TestStepResult {
-            // let r_test = ReaperTest::get_mut();
-            // let rpr = &mut r_test.reaper;
-            // for step in r_test.steps.iter() {
-            //     println!("Testing step: {}", step.name);
-            //     (step.operation)(rpr)?;
-            // }
-            ReaperTest::get()
-                .steps
-                .iter()
-                .map(|step| -> Result<(), Box<dyn Error>> {
-                    println!("Testing step: {}", step.name);
-                    let rpr = &mut ReaperTest::get_mut().reaper;
-                    (step.operation)(rpr)?;
-                    Ok(())
-                })
-                .count();
-            Ok(())
-        });
-        let final_result = match result.is_err() {
-            false => result.unwrap(),
-            true => Err("Reaper panicked!".into()),
-        };
-        match final_result {
-            Ok(_) => {
-                println!("From REAPER: reaper-rs integration test executed successfully");
-                if self.is_integration_test {
-                    process::exit(0)
-                }
-            }
-            Err(reason) => {
-                // We use a particular exit code to distinguish test
-                // failure from other possible
-                // exit paths.
-                match self.is_integration_test {
-                    true => {
-                        eprintln!("From REAPER: reaper-rs integration test failed: {}", reason);
-                        process::exit(172)
-                    }
-                    false => panic!("From REAPER: reaper-rs integration test failed: {}", reason),
-                }
-            }
-        }
-    }
-
     pub fn push_test_step(&mut self, step: TestStep) {
         self.steps.push(step);
     }
+
+    /// Run every step in its own fresh empty project tab.
+    ///
+    /// The tab is opened before `before_each` hooks and closed without
+    /// save prompt after `after_each` hooks. So tracks, items and markers,
+    /// left by one step, are not seen by the next one.
+    pub fn isolate_projects(&mut self, isolate: bool) {
+        self.isolate_projects = isolate;
+    }
+
+    /// Timeout of steps, which have not got their own by
+    /// [`TestStep::timeout`].
+    pub fn default_timeout(&mut self, timeout: Duration) {
+        self.default_timeout = Some(timeout);
+    }
+
+    /// Register hook, which is run once before the first step.
+    ///
+    /// If it fails, no step is run and all of them are reported as failed.
+    pub fn before_all(&mut self, hook: impl Fn(&'static mut Reaper) -> TestStepResult + 'static) {
+        self.before_all.push(Box::new(hook));
+    }
+
+    /// Register hook, which is run once after the last step, even if
+    /// some steps failed.
+    pub fn after_all(&mut self, hook: impl Fn(&'static mut Reaper) -> TestStepResult + 'static) {
+        self.after_all.push(Box::new(hook));
+    }
+
+    /// Register hook, which is run before every step.
+    ///
+    /// If it fails, the step is not run and reported as failed.
+    pub fn before_each(&mut self, hook: impl Fn(&'static mut Reaper) -> TestStepResult + 'static) {
+        self.before_each.push(Box::new(hook));
+    }
+
+    /// Register hook, which is run after every step, even if it failed or
+    /// panicked.
+    ///
+    /// Its failure is reported apart from the step outcome.
+    pub fn after_each(&mut self, hook: impl Fn(&'static mut Reaper) -> TestStepResult + 'static) {
+        self.after_each.push(Box::new(hook));
+    }
 }
@@ -0,0 +1,79 @@
+//! Capturing details of panics, which happen inside test steps.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    any::Any,
+    backtrace::{Backtrace, BacktraceStatus},
+    cell::RefCell,
+    fmt::Display,
+    panic,
+    sync::Once,
+};
+
+/// What is known about panic of a test step or hook.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PanicDetails {
+    /// Panic payload, if it is a string.
+    pub message: String,
+    /// `file:line:column` of the panic.
+    pub location: Option<String>,
+    /// Captured if `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` is set.
+    pub backtrace: Option<String>,
+}
+impl Display for PanicDetails {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{} (at {})", self.message, location),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+thread_local! {
+    /// Location and backtrace of the last panic, recorded by the hook.
+    static LAST_PANIC: RefCell<Option<(Option<String>, Option<String>)>> = const { RefCell::new(None) };
+}
+
+/// Installs panic hook, which records location and backtrace of every
+/// panic before calling the previous hook.
+pub(crate) fn install_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let location = info
+                .location()
+                .map(|l| format!("{}:{}:{}", l.file(), l.line(), l.column()));
+            let backtrace = Backtrace::capture();
+            let backtrace = match backtrace.status() {
+                BacktraceStatus::Captured => Some(backtrace.to_string()),
+                _ => None,
+            };
+            LAST_PANIC.with(|last| *last.borrow_mut() = Some((location, backtrace)));
+            previous(info);
+        }));
+    });
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+/// Combines payload, caught by `catch_unwind`, with details, recorded by
+/// the hook.
+pub(crate) fn panic_details(payload: &(dyn Any + Send)) -> PanicDetails {
+    let (location, backtrace) = LAST_PANIC
+        .with(|last| last.borrow_mut().take())
+        .unwrap_or_default();
+    PanicDetails {
+        message: panic_message(payload),
+        location,
+        backtrace,
+    }
+}
@@ -0,0 +1,318 @@
+//! Discovery of the test plugin, built by cargo.
+//!
+//! Plugin is the `cdylib` target of the package, which runs the
+//! integration test. Its name and the target directory are taken from
+//! `cargo metadata`, so they follow `CARGO_TARGET_DIR` and
+//! `build.target-dir` the same way as `cargo build` does.
+
+use crate::{integration_test::Result, IntegrationError, IntegrationTestConfig};
+use serde::Deserialize;
+use std::{
+    env::consts::{DLL_PREFIX, DLL_SUFFIX},
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    time::SystemTime,
+};
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Metadata {
+    pub packages: Vec<Package>,
+    pub target_directory: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Package {
+    pub name: String,
+    pub manifest_path: PathBuf,
+    pub targets: Vec<Target>,
+}
+impl Package {
+    fn cdylib(&self) -> Option<&Target> {
+        self.targets
+            .iter()
+            .find(|target| target.crate_types.iter().any(|kind| kind == "cdylib"))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct Target {
+    pub name: String,
+    pub crate_types: Vec<String>,
+}
+
+/// Built plugin library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PluginArtifact {
+    pub path: PathBuf,
+    /// File name inside `UserPlugins`. REAPER loads only extensions,
+    /// which names start with `reaper_`.
+    pub install_name: String,
+}
+
+/// Cargo workspace of the package, which runs the integration test.
+///
+/// Resolved once per run, so `cargo metadata` is invoked only once.
+pub(crate) struct Workspace {
+    pub manifest_dir: PathBuf,
+    /// Configured target directory or the one of the workspace.
+    pub target_dir: PathBuf,
+    metadata: std::result::Result<Metadata, String>,
+}
+impl Workspace {
+    /// Target dir falls back to `CARGO_TARGET_DIR` or `../target` if
+    /// `cargo metadata` can not be run.
+    pub fn resolve(config: &IntegrationTestConfig) -> Result<Self> {
+        let manifest_dir = manifest_dir()?;
+        let metadata = cargo_metadata(&manifest_dir).map_err(|err| err.to_string());
+        let target_dir = match (&config.target_dir, &metadata) {
+            (Some(path), _) => path.clone(),
+            (None, Ok(metadata)) => metadata.target_directory.clone(),
+            (None, Err(err)) => {
+                log::warn!("{}", err);
+                std::env::var_os("CARGO_TARGET_DIR")
+                    .map_or_else(|| manifest_dir.join("../target"), PathBuf::from)
+            }
+        };
+        Ok(Self {
+            manifest_dir,
+            target_dir,
+            metadata,
+        })
+    }
+
+    fn metadata(&self) -> Result<&Metadata> {
+        self.metadata
+            .as_ref()
+            .map_err(|err| IntegrationError::CargoFailed(err.clone()))
+    }
+}
+
+/// Directory of the package, which runs the integration test.
+fn manifest_dir() -> Result<PathBuf> {
+    std::env::var_os("CARGO_MANIFEST_DIR")
+        .map(PathBuf::from)
+        .ok_or_else(|| {
+            IntegrationError::CargoFailed(
+                "CARGO_MANIFEST_DIR is not set, integration test should be run by cargo"
+                    .to_string(),
+            )
+        })
+}
+
+/// Runs `cargo metadata` for the workspace of the package.
+fn cargo_metadata(manifest_dir: &Path) -> Result<Metadata> {
+    let output = Command::new(cargo())
+        .args(["metadata", "--format-version", "1", "--no-deps"])
+        .arg("--manifest-path")
+        .arg(manifest_dir.join("Cargo.toml"))
+        .output()
+        .map_err(|err| IntegrationError::CargoFailed(format!("can not run cargo: {}", err)))?;
+    if !output.status.success() {
+        return Err(IntegrationError::CargoFailed(format!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    serde_json::from_slice(&output.stdout).map_err(|err| {
+        IntegrationError::CargoFailed(format!("can not parse cargo metadata: {}", err))
+    })
+}
+
+/// Cargo, which runs the test, or the one from `PATH`.
+pub(crate) fn cargo() -> PathBuf {
+    std::env::var_os("CARGO").map_or_else(|| PathBuf::from("cargo"), PathBuf::from)
+}
+
+/// Finds the `cdylib` target of the calling package.
+///
+/// If the package has none, the only `cdylib` of the workspace is taken.
+pub(crate) fn find_plugin_package<'a>(
+    metadata: &'a Metadata,
+    manifest_dir: &Path,
+) -> Result<&'a Package> {
+    let is_caller = |package: &&Package| {
+        package.manifest_path.parent().map(canonical) == Some(canonical(manifest_dir))
+    };
+    if let Some(package) = metadata
+        .packages
+        .iter()
+        .find(is_caller)
+        .filter(|package| package.cdylib().is_some())
+    {
+        return Ok(package);
+    }
+    let candidates: Vec<&Package> = metadata
+        .packages
+        .iter()
+        .filter(|package| package.cdylib().is_some())
+        .collect();
+    match candidates.as_slice() {
+        [package] => Ok(package),
+        [] => Err(IntegrationError::CargoFailed(
+            "no cdylib target in the workspace".to_string(),
+        )),
+        packages => Err(IntegrationError::CargoFailed(format!(
+            "can not choose test plugin among {}, set IntegrationTestConfig::plugin_name",
+            packages
+                .iter()
+                .map(|package| package.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+/// Package, which builds the plugin.
+fn plugin_package<'a>(
+    config: &IntegrationTestConfig,
+    workspace: &'a Workspace,
+) -> Result<&'a Package> {
+    let metadata = workspace.metadata()?;
+    let name = match &config.plugin_name {
+        Some(name) => name,
+        None => return find_plugin_package(metadata, &workspace.manifest_dir),
+    };
+    metadata
+        .packages
+        .iter()
+        .find(|package| {
+            package
+                .cdylib()
+                .is_some_and(|target| &target.name.replace('-', "_") == name)
+        })
+        .ok_or_else(|| {
+            IntegrationError::CargoFailed(format!("no cdylib named {} in the workspace", name))
+        })
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Library name of the plugin, as it is passed to rustc.
+pub(crate) fn plugin_lib_name(
+    config: &IntegrationTestConfig,
+    workspace: &Workspace,
+) -> Result<String> {
+    if let Some(name) = &config.plugin_name {
+        return Ok(name.clone());
+    }
+    let package = find_plugin_package(workspace.metadata()?, &workspace.manifest_dir)?;
+    let target = package.cdylib().expect("checked by find_plugin_package");
+    Ok(target.name.replace('-', "_"))
+}
+
+/// Directory, where cargo puts artifacts of the configured profile.
+pub(crate) fn artifact_dir(config: &IntegrationTestConfig, workspace: &Workspace) -> PathBuf {
+    let mut path = workspace.target_dir.clone();
+    if let Some(triple) = config.target_triple() {
+        path.push(triple);
+    }
+    path.push(match config.profile.as_str() {
+        "dev" | "test" => "debug",
+        "bench" => "release",
+        profile => profile,
+    });
+    path
+}
+
+/// Locates the built plugin.
+pub(crate) fn find_plugin(
+    config: &IntegrationTestConfig,
+    workspace: &Workspace,
+) -> Result<PluginArtifact> {
+    let lib_name = plugin_lib_name(config, workspace)?;
+    let path =
+        artifact_dir(config, workspace).join(format!("{}{}{}", DLL_PREFIX, lib_name, DLL_SUFFIX));
+    if !path.exists() {
+        return Err(IntegrationError::PluginNotFound(path));
+    }
+    let install_name = match lib_name.starts_with("reaper_") {
+        true => format!("{}{}", lib_name, DLL_SUFFIX),
+        false => format!("reaper_{}{}", lib_name, DLL_SUFFIX),
+    };
+    Ok(PluginArtifact { path, install_name })
+}
+
+/// Runs `cargo build` for the plugin with the configured profile, target
+/// and features.
+pub(crate) fn build_plugin(config: &IntegrationTestConfig, workspace: &Workspace) -> Result<()> {
+    let package = plugin_package(config, workspace)?;
+    let mut command = Command::new(cargo());
+    command
+        .arg("build")
+        .arg("--lib")
+        .arg("--manifest-path")
+        .arg(&package.manifest_path)
+        .arg("--profile")
+        .arg(&config.profile);
+    if let Some(triple) = config.target_triple() {
+        command.arg("--target").arg(triple);
+    }
+    if let Some(path) = &config.target_dir {
+        command.arg("--target-dir").arg(path);
+    }
+    if !config.features.is_empty() {
+        command.arg("--features").arg(config.features.join(","));
+    }
+    println!("Building plug-in {}...", package.name);
+    let status = command
+        .status()
+        .map_err(|err| IntegrationError::CargoFailed(format!("can not run cargo: {}", err)))?;
+    if !status.success() {
+        return Err(IntegrationError::CargoFailed(format!(
+            "cargo build of {} failed ({})",
+            package.name, status
+        )));
+    }
+    Ok(())
+}
+
+/// Refuses the plugin, if any source of its package is newer.
+///
+/// Only `Cargo.toml`, `build.rs` and the `src` dir are checked, changes
+/// of dependencies are not noticed.
+pub(crate) fn check_plugin_is_fresh(
+    config: &IntegrationTestConfig,
+    workspace: &Workspace,
+    plugin: &PluginArtifact,
+) -> Result<()> {
+    let package = plugin_package(config, workspace)?;
+    let package_dir = package
+        .manifest_path
+        .parent()
+        .expect("manifest is always in a directory");
+    let built = fs::metadata(&plugin.path)?.modified()?;
+    let mut newest = None;
+    for path in [
+        package_dir.join("Cargo.toml"),
+        package_dir.join("build.rs"),
+        package_dir.join("src"),
+    ] {
+        newest = newest.max(last_modified(&path)?);
+    }
+    match newest {
+        Some(modified) if modified > built => {
+            Err(IntegrationError::PluginOutdated(plugin.path.clone()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The latest modification time of the file or of files in the dir.
+fn last_modified(path: &Path) -> io::Result<Option<SystemTime>> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    if !metadata.is_dir() {
+        return Ok(Some(metadata.modified()?));
+    }
+    let mut newest = None;
+    for entry in fs::read_dir(path)? {
+        newest = newest.max(last_modified(&entry?.path())?);
+    }
+    Ok(newest)
+}
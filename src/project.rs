@@ -0,0 +1,90 @@
+//! Helpers for switching REAPER projects and quitting REAPER without save
+//! prompts.
+
+use rea_rs::Reaper;
+use std::{
+    ffi::{CStr, CString},
+    fs,
+    path::PathBuf,
+    ptr::null_mut,
+    sync::OnceLock,
+};
+
+/// "File: New project tab"
+const NEW_PROJECT_TAB: i32 = 40859;
+/// "File: Close current project tab"
+const CLOSE_PROJECT_TAB: i32 = 40860;
+/// "File: Quit REAPER"
+const QUIT_REAPER: i32 = 40004;
+
+/// Path of empty project file inside the resource directory, which is
+/// private for the run.
+///
+/// Loading it makes current project clean, so it can be closed without
+/// prompting.
+fn blank_project_path(reaper: &Reaper) -> Result<&'static PathBuf, String> {
+    static PATH: OnceLock<PathBuf> = OnceLock::new();
+    if let Some(path) = PATH.get() {
+        return Ok(path);
+    }
+    let resource_path = unsafe { CStr::from_ptr(reaper.low().GetResourcePath()) };
+    let path =
+        PathBuf::from(resource_path.to_string_lossy().into_owned()).join("reaper-test-blank.RPP");
+    fs::write(&path, "<REAPER_PROJECT 0.1\n>\n")
+        .map_err(|err| format!("can not write blank project {:?}: {}", path, err))?;
+    Ok(PATH.get_or_init(|| path))
+}
+
+/// Replaces current project by the empty one, discarding unsaved changes.
+pub(crate) fn discard_current_project(reaper: &Reaper) -> Result<(), String> {
+    let path = format!("noprompt:{}", blank_project_path(reaper)?.display());
+    let path = CString::new(path).map_err(|_| "project path contains nul byte".to_string())?;
+    unsafe { reaper.low().Main_openProject(path.as_ptr()) };
+    Ok(())
+}
+
+/// Opens new empty project tab and makes it current.
+pub(crate) fn open_project_tab(reaper: &Reaper) {
+    unsafe {
+        reaper
+            .low()
+            .Main_OnCommandEx(NEW_PROJECT_TAB, 0, null_mut())
+    }
+}
+
+/// Closes current project tab without save prompt.
+///
+/// If unsaved changes can not be discarded, the tab is left open, since
+/// closing it would block REAPER by the prompt.
+pub(crate) fn close_project_tab(reaper: &Reaper) -> Result<(), String> {
+    discard_current_project(reaper)?;
+    unsafe {
+        reaper
+            .low()
+            .Main_OnCommandEx(CLOSE_PROJECT_TAB, 0, null_mut())
+    };
+    Ok(())
+}
+
+/// Discards unsaved changes of all open projects and asks REAPER to quit.
+///
+/// REAPER quits after the current callback returns. If changes can not be
+/// discarded, REAPER is asked to quit anyway: the launcher kills it, if it
+/// hangs on the prompt.
+pub(crate) fn quit_reaper(reaper: &Reaper) {
+    let low = reaper.low();
+    let mut idx = 0;
+    loop {
+        let project = unsafe { low.EnumProjects(idx, null_mut(), 0) };
+        if project.is_null() {
+            break;
+        }
+        unsafe { low.SelectProjectInstance(project) };
+        if let Err(error) = discard_current_project(reaper) {
+            eprintln!("Can not discard unsaved changes before quit: {}", error);
+            break;
+        }
+        idx += 1;
+    }
+    unsafe { low.Main_OnCommandEx(QUIT_REAPER, 0, null_mut()) }
+}
@@ -0,0 +1,308 @@
+//! Configuration of the tested REAPER, written into its resource directory
+//! before launch.
+
+use std::{
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Sections of an ini file in order of insertion.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Ini {
+    sections: Vec<(String, Vec<(String, String)>)>,
+}
+impl Ini {
+    fn set(&mut self, section: &str, key: &str, value: impl Into<String>) {
+        let entries = match self.sections.iter().position(|(name, _)| name == section) {
+            Some(idx) => &mut self.sections[idx].1,
+            None => {
+                self.sections.push((section.to_string(), Vec::new()));
+                &mut self.sections.last_mut().expect("just pushed").1
+            }
+        };
+        let value = value.into();
+        match entries.iter_mut().find(|(name, _)| name == key) {
+            Some(entry) => entry.1 = value,
+            None => entries.push((key.to_string(), value)),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    fn render(&self) -> String {
+        let mut content = String::new();
+        for (section, entries) in self.sections.iter() {
+            // Writing into String never fails.
+            let _ = writeln!(content, "[{}]", section);
+            for (key, value) in entries.iter() {
+                let _ = writeln!(content, "{}={}", key, value);
+            }
+            content.push('\n');
+        }
+        content
+    }
+}
+
+/// Contents of `reaper.ini` and other resource files.
+///
+/// By default REAPER is set up for dummy audio, so it runs on machines
+/// without sound card.
+///
+/// ```ignore
+/// use reaper_test::{IntegrationTestConfig, ReaperConfig, ReaperVersion};
+///
+/// IntegrationTestConfig::new(ReaperVersion::latest())
+///     .reaper_config(
+///         ReaperConfig::new()
+///             .sample_rate(48000)
+///             .block_size(256)
+///             .update_check(false)
+///             .extstate("MyExtension", "mode", "test"),
+///     )
+///     .run();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReaperConfig {
+    ini: Ini,
+    extstate: Ini,
+    splash: bool,
+    files: Vec<(PathBuf, String)>,
+}
+impl ReaperConfig {
+    pub fn new() -> Self {
+        let mut ini = Ini::default();
+        // For dummy audio on Windows
+        ini.set("audioconfig", "mode", "4");
+        // For dummy audio on Linux
+        ini.set("REAPER", "linux_audio_mode", "2");
+        // For <none> audio on macOS
+        ini.set("REAPER", "coreaudiobs", "512");
+        ini.set("REAPER", "coreaudioindevnew", "<none>");
+        ini.set("REAPER", "coreaudiooutdevnew", "<none>");
+        Self {
+            ini,
+            extstate: Ini::default(),
+            splash: true,
+            files: Vec::new(),
+        }
+    }
+
+    /// Sets arbitrary `key=value` entry of `[section]` in `reaper.ini`.
+    pub fn set(mut self, section: &str, key: &str, value: impl ToString) -> Self {
+        self.ini.set(section, key, value.to_string());
+        self
+    }
+
+    /// Sets `key=value` entry of the `[REAPER]` section.
+    fn set_reaper(self, key: &str, value: impl ToString) -> Self {
+        self.set("REAPER", key, value)
+    }
+
+    /// Sample rate of the audio device.
+    pub fn sample_rate(self, rate: u32) -> Self {
+        self.set_reaper("linux_audio_srate", rate)
+            .set_reaper("coreaudiosrate", rate)
+    }
+
+    /// Block size of the audio device.
+    pub fn block_size(self, size: u32) -> Self {
+        self.set_reaper("linux_audio_bsize", size)
+            .set_reaper("coreaudiobs", size)
+    }
+
+    /// Maximum memory, used by undo history, in megabytes.
+    pub fn undo_memory_limit(self, megabytes: u32) -> Self {
+        self.set_reaper("undomaxmem", megabytes)
+    }
+
+    /// Interval of project auto-save. `None` turns it off.
+    pub fn autosave(self, interval: Option<Duration>) -> Self {
+        let minutes = interval.map_or(0, |interval| (interval.as_secs() / 60).max(1));
+        self.set_reaper("autosaveint", minutes)
+    }
+
+    /// Whether to show the splash screen on start (passed as
+    /// `-nosplash` argument).
+    pub fn splash(mut self, show: bool) -> Self {
+        self.splash = show;
+        self
+    }
+
+    /// Whether REAPER checks for updates on start.
+    pub fn update_check(self, check: bool) -> Self {
+        self.set_reaper("verchk", check as u8)
+    }
+
+    /// Project template, used for new projects.
+    pub fn project_template(self, path: impl AsRef<Path>) -> Self {
+        self.set_reaper("newprojtmpl", path.as_ref().display())
+    }
+
+    /// Default path of recorded media.
+    pub fn record_path(self, path: impl AsRef<Path>) -> Self {
+        self.set_reaper("defrecpath", path.as_ref().display())
+    }
+
+    /// Default path of rendered media.
+    pub fn render_path(self, path: impl AsRef<Path>) -> Self {
+        self.set_reaper("defrenderpath", path.as_ref().display())
+    }
+
+    /// Persistent ExtState value, put into `reaper-extstate.ini`.
+    pub fn extstate(mut self, section: &str, key: &str, value: impl ToString) -> Self {
+        self.extstate.set(section, key, value.to_string());
+        self
+    }
+
+    /// Content of `reaper-kb.ini` (key bindings and custom actions).
+    pub fn key_bindings(self, content: impl Into<String>) -> Self {
+        self.file("reaper-kb.ini", content)
+    }
+
+    /// Arbitrary file, relative to the resource directory.
+    ///
+    /// Files are written after `reaper.ini` and `reaper-extstate.ini`, so
+    /// a file with one of these names replaces the rendered content and
+    /// all settings of this config, which go into it.
+    pub fn file(mut self, path: impl Into<PathBuf>, content: impl Into<String>) -> Self {
+        self.files.push((path.into(), content.into()));
+        self
+    }
+
+    /// Content of `reaper.ini`.
+    pub fn render(&self) -> String {
+        self.ini.render()
+    }
+
+    /// Command line arguments, which can not be set by files.
+    pub(crate) fn launch_args(&self) -> Vec<&'static str> {
+        match self.splash {
+            true => Vec::new(),
+            false => vec!["-nosplash"],
+        }
+    }
+
+    /// Writes `reaper.ini` and seeded files into the resource directory.
+    pub fn write(&self, resource_path: &Path) -> io::Result<()> {
+        fs::create_dir_all(resource_path)?;
+        fs::write(resource_path.join("reaper.ini"), self.render())?;
+        if !self.extstate.is_empty() {
+            fs::write(
+                resource_path.join("reaper-extstate.ini"),
+                self.extstate.render(),
+            )?;
+        }
+        for (path, content) in self.files.iter() {
+            let path = resource_path.join(path);
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, content)?;
+        }
+        Ok(())
+    }
+}
+impl Default for ReaperConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ini_keeps_order_and_overrides_in_place() {
+        let mut ini = Ini::default();
+        assert!(ini.is_empty());
+        ini.set("REAPER", "a", "1");
+        ini.set("audioconfig", "mode", "4");
+        ini.set("REAPER", "b", "2");
+        ini.set("REAPER", "a", "3");
+        assert_eq!(
+            ini.render(),
+            "[REAPER]\na=3\nb=2\n\n[audioconfig]\nmode=4\n\n"
+        );
+    }
+
+    #[test]
+    fn render_defaults_and_overrides() {
+        let rendered = ReaperConfig::new()
+            .block_size(256)
+            .sample_rate(48000)
+            .update_check(false)
+            .autosave(Some(Duration::from_secs(30)))
+            .set("audioconfig", "mode", 2)
+            .render();
+        assert_eq!(
+            rendered,
+            "[audioconfig]\n\
+             mode=2\n\
+             \n\
+             [REAPER]\n\
+             linux_audio_mode=2\n\
+             coreaudiobs=256\n\
+             coreaudioindevnew=<none>\n\
+             coreaudiooutdevnew=<none>\n\
+             linux_audio_bsize=256\n\
+             linux_audio_srate=48000\n\
+             coreaudiosrate=48000\n\
+             verchk=0\n\
+             autosaveint=1\n\
+             \n"
+        );
+        assert!(ReaperConfig::new()
+            .autosave(None)
+            .render()
+            .contains("autosaveint=0\n"));
+    }
+
+    #[test]
+    fn splash_is_turned_off_by_argument() {
+        assert!(ReaperConfig::new().launch_args().is_empty());
+        assert_eq!(
+            ReaperConfig::new().splash(false).launch_args(),
+            vec!["-nosplash"]
+        );
+    }
+
+    #[test]
+    fn write_resource_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let resource_path = dir.path().join("resources");
+        let config = ReaperConfig::new()
+            .extstate("MyExtension", "mode", "test")
+            .key_bindings("KEY 1 65 _MY_ACTION 0\n")
+            .file("Scripts/hello.lua", "print('hello')");
+        config.write(&resource_path).unwrap();
+        let read = |path: &str| fs::read_to_string(resource_path.join(path)).unwrap();
+        assert_eq!(read("reaper.ini"), config.render());
+        assert_eq!(read("reaper-extstate.ini"), "[MyExtension]\nmode=test\n\n");
+        assert_eq!(read("reaper-kb.ini"), "KEY 1 65 _MY_ACTION 0\n");
+        assert_eq!(read("Scripts/hello.lua"), "print('hello')");
+
+        let dir = tempfile::tempdir().unwrap();
+        ReaperConfig::new().write(dir.path()).unwrap();
+        assert!(!dir.path().join("reaper-extstate.ini").exists());
+    }
+
+    #[test]
+    fn seeded_files_take_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        ReaperConfig::new()
+            .sample_rate(48000)
+            .extstate("MyExtension", "mode", "test")
+            .file("reaper.ini", "[REAPER]\ncustom=1\n")
+            .file("reaper-extstate.ini", "[Other]\nkey=value\n")
+            .write(dir.path())
+            .unwrap();
+        let read = |path: &str| fs::read_to_string(dir.path().join(path)).unwrap();
+        assert_eq!(read("reaper.ini"), "[REAPER]\ncustom=1\n");
+        assert_eq!(read("reaper-extstate.ini"), "[Other]\nkey=value\n");
+    }
+}
@@ -0,0 +1,517 @@
+//! Machine-readable channel between the plugin inside REAPER and
+//! [`run_integration_test`](crate::run_integration_test).
+//!
+//! The launcher names a file through the [`REPORT_PATH_ENV`] environment
+//! variable. The plugin appends one JSON-encoded [`ReportEvent`] per line
+//! to it while the suite runs, so even a crashed or killed REAPER leaves
+//! everything that happened before behind. The launcher reads it back into
+//! a [`TestReport`].
+
+use crate::StepOutcome;
+use log::{LevelFilter, Log, Metadata, Record};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Display,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::Path,
+    sync::{Mutex, OnceLock},
+    time::Duration,
+};
+
+/// Environment variable holding path of the report file.
+pub const REPORT_PATH_ENV: &str = "REAPER_TEST_REPORT";
+
+/// Step, registered by [`ReaperTest::push_test_step`](crate::ReaperTest::push_test_step).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepInfo {
+    pub name: String,
+    #[serde(default)]
+    pub ignored: bool,
+}
+
+/// Single line of the report.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ReportEvent {
+    SuiteStarted {
+        steps: Vec<StepInfo>,
+        /// `GetAppVersion()` of the running REAPER, e.g. `7.22/linux-x86_64`.
+        #[serde(default)]
+        reaper_version: Option<String>,
+    },
+    StepStarted {
+        name: String,
+        #[serde(default)]
+        timeout_secs: Option<f64>,
+    },
+    Log {
+        step: Option<String>,
+        level: String,
+        message: String,
+    },
+    StepFinished {
+        name: String,
+        outcome: StepOutcome,
+        duration_secs: f64,
+        #[serde(default)]
+        teardown_error: Option<String>,
+    },
+    SuiteFinished {
+        duration_secs: f64,
+        /// Failure of `before_all` hooks.
+        #[serde(default)]
+        setup_error: Option<String>,
+        /// Failure of `after_all` hooks.
+        #[serde(default)]
+        teardown_error: Option<String>,
+    },
+}
+
+/// Writes [`ReportEvent`]s into the file named by [`REPORT_PATH_ENV`].
+///
+/// Does nothing if the variable is not set, e.g. when tests are invoked
+/// by action from the running REAPER.
+pub(crate) struct ReportWriter {
+    inner: Mutex<WriterState>,
+}
+struct WriterState {
+    file: Option<File>,
+    current_step: Option<String>,
+}
+impl ReportWriter {
+    fn from_env() -> Self {
+        let file = std::env::var_os(REPORT_PATH_ENV).and_then(|path| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(|err| eprintln!("Can not open test report: {}", err))
+                .ok()
+        });
+        Self {
+            inner: Mutex::new(WriterState {
+                file,
+                current_step: None,
+            }),
+        }
+    }
+
+    pub fn emit(&self, event: ReportEvent) {
+        let mut state = match self.inner.lock() {
+            Ok(state) => state,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        match &event {
+            ReportEvent::StepStarted { name, .. } => state.current_step = Some(name.clone()),
+            ReportEvent::StepFinished { .. } => state.current_step = None,
+            _ => (),
+        }
+        if let Some(file) = state.file.as_mut() {
+            let line = serde_json::to_string(&event).expect("can not serialize report event");
+            if let Err(err) = writeln!(file, "{}", line).and_then(|_| file.flush()) {
+                eprintln!("Can not write test report: {}", err);
+            }
+        }
+    }
+
+    fn log(&self, level: String, message: String) {
+        let step = match self.inner.lock() {
+            Ok(state) => state.current_step.clone(),
+            Err(poisoned) => poisoned.into_inner().current_step.clone(),
+        };
+        self.emit(ReportEvent::Log {
+            step,
+            level,
+            message,
+        });
+    }
+}
+
+/// Global writer, shared between the test runner and [`ReportLogger`].
+pub(crate) fn writer() -> &'static ReportWriter {
+    static WRITER: OnceLock<ReportWriter> = OnceLock::new();
+    WRITER.get_or_init(ReportWriter::from_env)
+}
+
+/// Logger, which prints records to stderr and puts them into the report,
+/// attached to the step being run.
+pub(crate) struct ReportLogger;
+impl ReportLogger {
+    /// Installs logger with the level taken from `RUST_LOG`.
+    ///
+    /// Does nothing if another logger has already been set.
+    pub fn install() {
+        let level = std::env::var("RUST_LOG")
+            .ok()
+            .and_then(|level| level.parse().ok())
+            .unwrap_or(LevelFilter::Info);
+        if log::set_boxed_logger(Box::new(ReportLogger)).is_ok() {
+            log::set_max_level(level);
+        }
+    }
+}
+impl Log for ReportLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = record.args().to_string();
+        eprintln!("[{} {}] {}", record.level(), record.target(), message);
+        writer().log(record.level().to_string(), message);
+    }
+
+    fn flush(&self) {}
+}
+
+/// Report of a single [`TestStep`](crate::TestStep) as seen by the launcher.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepReport {
+    pub name: String,
+    /// Step is marked by [`TestStep::ignore`](crate::TestStep::ignore).
+    pub ignored: bool,
+    /// `true` if step has been started.
+    pub started: bool,
+    pub timeout: Option<Duration>,
+    /// `None` if step has not been finished.
+    pub outcome: Option<StepOutcome>,
+    /// Failure of `after_each` hooks.
+    pub teardown_error: Option<String>,
+    pub duration: Duration,
+    pub logs: Vec<String>,
+}
+impl StepReport {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            ignored: false,
+            started: false,
+            timeout: None,
+            outcome: None,
+            teardown_error: None,
+            duration: Duration::ZERO,
+            logs: Vec::new(),
+        }
+    }
+
+    /// `true` if step failed, panicked, has not been finished or its
+    /// teardown failed.
+    pub fn is_failure(&self) -> bool {
+        self.outcome.as_ref().is_none_or(StepOutcome::is_failure) || self.teardown_error.is_some()
+    }
+}
+
+/// Everything the plugin reported during the integration test run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TestReport {
+    pub steps: Vec<StepReport>,
+    /// Log records emitted outside of any step.
+    pub logs: Vec<String>,
+    /// `true` if the suite has been run till the end.
+    pub finished: bool,
+    /// Failure of `before_all` hooks.
+    pub setup_error: Option<String>,
+    /// Failure of `after_all` hooks.
+    pub teardown_error: Option<String>,
+    pub duration: Duration,
+    /// Reason, by which the launcher stopped REAPER before the suite end.
+    pub aborted: Option<String>,
+    /// Version of REAPER, which has run the suite, as reported by REAPER
+    /// itself.
+    pub reaper_version: Option<String>,
+}
+impl TestReport {
+    /// Reads report, written by the plugin.
+    ///
+    /// Lines, which can not be parsed (e.g. the last one, if REAPER
+    /// crashed in the middle of writing) are skipped.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        let mut report = Self::default();
+        for line in BufReader::new(File::open(path)?).lines() {
+            match serde_json::from_str(&line?) {
+                Ok(event) => report.apply(event),
+                Err(err) => log::warn!("Skipping malformed report line: {}", err),
+            }
+        }
+        Ok(report)
+    }
+
+    pub fn apply(&mut self, event: ReportEvent) {
+        match event {
+            ReportEvent::SuiteStarted {
+                steps,
+                reaper_version,
+            } => {
+                self.reaper_version = reaper_version;
+                self.steps = steps
+                    .into_iter()
+                    .map(|info| StepReport {
+                        ignored: info.ignored,
+                        ..StepReport::new(info.name)
+                    })
+                    .collect()
+            }
+            ReportEvent::StepStarted { name, timeout_secs } => {
+                let step = self.step_mut(name);
+                step.started = true;
+                step.timeout = timeout_secs.map(Duration::from_secs_f64);
+            }
+            ReportEvent::Log {
+                step,
+                level,
+                message,
+            } => {
+                let line = format!("[{}] {}", level, message);
+                match step {
+                    Some(name) => self.step_mut(name).logs.push(line),
+                    None => self.logs.push(line),
+                }
+            }
+            ReportEvent::StepFinished {
+                name,
+                outcome,
+                duration_secs,
+                teardown_error,
+            } => {
+                let step = self.step_mut(name);
+                step.outcome = Some(outcome);
+                step.teardown_error = teardown_error;
+                step.duration = Duration::from_secs_f64(duration_secs);
+            }
+            ReportEvent::SuiteFinished {
+                duration_secs,
+                setup_error,
+                teardown_error,
+            } => {
+                self.finished = true;
+                self.duration = Duration::from_secs_f64(duration_secs);
+                self.setup_error = setup_error;
+                self.teardown_error = teardown_error;
+            }
+        }
+    }
+
+    fn step_mut(&mut self, name: String) -> &mut StepReport {
+        match self.steps.iter().position(|step| step.name == name) {
+            Some(idx) => &mut self.steps[idx],
+            None => {
+                self.steps.push(StepReport::new(name));
+                self.steps.last_mut().expect("just pushed")
+            }
+        }
+    }
+
+    /// Step, which has been started, but not finished.
+    pub fn running_step(&self) -> Option<&StepReport> {
+        self.steps
+            .iter()
+            .find(|step| step.started && step.outcome.is_none())
+    }
+
+    /// Marks running step as timed out after `elapsed` time.
+    pub fn time_out_running_step(&mut self, elapsed: Duration) {
+        if let Some(name) = self.running_step().map(|step| step.name.clone()) {
+            self.step_mut(name).outcome = Some(StepOutcome::TimedOut(elapsed));
+        }
+    }
+
+    /// Records, that REAPER stopped before the suite end.
+    pub fn abort(&mut self, reason: impl Into<String>) {
+        self.aborted = Some(reason.into());
+    }
+
+    /// `true` if suite has been finished and neither step nor hook
+    /// failed.
+    pub fn is_success(&self) -> bool {
+        self.finished
+            && self.setup_error.is_none()
+            && self.teardown_error.is_none()
+            && self.failures().next().is_none()
+    }
+
+    /// Steps, which failed or have not been finished.
+    pub fn failures(&self) -> impl Iterator<Item = &StepReport> {
+        self.steps.iter().filter(|step| step.is_failure())
+    }
+
+    pub fn step(&self, name: &str) -> Option<&StepReport> {
+        self.steps.iter().find(|step| step.name == name)
+    }
+}
+impl Display for TestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for step in self.steps.iter() {
+            match &step.outcome {
+                Some(outcome) => writeln!(f, "{} ... {}", step.name, outcome)?,
+                None => writeln!(f, "{} ... not finished", step.name)?,
+            }
+            if let Some(error) = &step.teardown_error {
+                writeln!(f, "    teardown: {}", error)?;
+            }
+        }
+        for error in self.setup_error.iter().chain(self.teardown_error.iter()) {
+            writeln!(f, "{}", error)?;
+        }
+        if let Some(reason) = &self.aborted {
+            writeln!(f, "aborted: {}", reason)?;
+        } else if !self.finished {
+            writeln!(f, "suite has not been finished")?;
+        }
+        let count = |outcome: StepOutcome| {
+            self.steps
+                .iter()
+                .filter(|step| step.outcome.as_ref() == Some(&outcome))
+                .count()
+        };
+        write!(
+            f,
+            "test result: {}. {} passed; {} failed; {} ignored; {} filtered out",
+            if self.is_success() { "ok" } else { "FAILED" },
+            count(StepOutcome::Passed),
+            self.failures().count(),
+            count(StepOutcome::Ignored),
+            count(StepOutcome::FilteredOut)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PanicDetails;
+
+    fn step_finished(name: &str, outcome: StepOutcome) -> ReportEvent {
+        ReportEvent::StepFinished {
+            name: name.to_string(),
+            outcome,
+            duration_secs: 0.5,
+            teardown_error: None,
+        }
+    }
+
+    fn suite_started(names: &[&str]) -> ReportEvent {
+        ReportEvent::SuiteStarted {
+            steps: names
+                .iter()
+                .map(|name| StepInfo {
+                    name: name.to_string(),
+                    ignored: false,
+                })
+                .collect(),
+            reaper_version: Some("7.22/linux-x86_64".into()),
+        }
+    }
+
+    #[test]
+    fn apply_tracks_steps_and_logs() {
+        let mut report = TestReport::default();
+        report.apply(suite_started(&["first", "second"]));
+        report.apply(ReportEvent::StepStarted {
+            name: "first".into(),
+            timeout_secs: Some(2.0),
+        });
+        report.apply(ReportEvent::Log {
+            step: Some("first".into()),
+            level: "INFO".into(),
+            message: "inside".into(),
+        });
+        report.apply(ReportEvent::Log {
+            step: None,
+            level: "WARN".into(),
+            message: "outside".into(),
+        });
+        assert_eq!(
+            report.running_step().map(|s| s.name.as_str()),
+            Some("first")
+        );
+        report.apply(step_finished("first", StepOutcome::Passed));
+        report.apply(step_finished(
+            "second",
+            StepOutcome::Panicked(PanicDetails {
+                message: "boom".into(),
+                location: None,
+                backtrace: None,
+            }),
+        ));
+        report.apply(ReportEvent::SuiteFinished {
+            duration_secs: 1.0,
+            setup_error: None,
+            teardown_error: None,
+        });
+
+        let first = report.step("first").unwrap();
+        assert_eq!(first.timeout, Some(Duration::from_secs(2)));
+        assert_eq!(first.logs, vec!["[INFO] inside".to_string()]);
+        assert_eq!(first.duration, Duration::from_millis(500));
+        assert_eq!(report.logs, vec!["[WARN] outside".to_string()]);
+        assert!(report.running_step().is_none());
+        assert!(report.finished);
+        assert_eq!(report.reaper_version.as_deref(), Some("7.22/linux-x86_64"));
+        assert_eq!(
+            report
+                .failures()
+                .map(|s| s.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["second"]
+        );
+        assert!(!report.is_success());
+    }
+
+    #[test]
+    fn suite_teardown_error_fails_report() {
+        let mut report = TestReport::default();
+        report.apply(suite_started(&["only"]));
+        report.apply(step_finished("only", StepOutcome::Passed));
+        report.apply(ReportEvent::SuiteFinished {
+            duration_secs: 1.0,
+            setup_error: None,
+            teardown_error: Some("after_all hook FAILED: oops".into()),
+        });
+        assert_eq!(report.failures().count(), 0);
+        assert!(!report.is_success());
+    }
+
+    #[test]
+    fn unfinished_step_is_failure() {
+        let mut report = TestReport::default();
+        report.apply(suite_started(&["hung"]));
+        report.apply(ReportEvent::StepStarted {
+            name: "hung".into(),
+            timeout_secs: None,
+        });
+        report.time_out_running_step(Duration::from_secs(3));
+        assert_eq!(
+            report.step("hung").unwrap().outcome,
+            Some(StepOutcome::TimedOut(Duration::from_secs(3)))
+        );
+        assert!(!report.finished);
+        assert!(!report.is_success());
+    }
+
+    #[test]
+    fn read_skips_truncated_last_line() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for event in [
+            suite_started(&["first", "second"]),
+            step_finished("first", StepOutcome::Failed("wrong".into())),
+        ] {
+            writeln!(file, "{}", serde_json::to_string(&event).unwrap()).unwrap();
+        }
+        // REAPER crashed in the middle of writing.
+        write!(file, "{{\"event\":\"step_fin").unwrap();
+        file.flush().unwrap();
+
+        let report = TestReport::read(file.path()).unwrap();
+        assert_eq!(report.steps.len(), 2);
+        assert_eq!(
+            report.step("first").unwrap().outcome,
+            Some(StepOutcome::Failed("wrong".into()))
+        );
+        assert_eq!(report.step("second").unwrap().outcome, None);
+        assert!(!report.finished);
+    }
+}
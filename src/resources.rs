@@ -0,0 +1,107 @@
+//! Extra files, installed into the REAPER resource directory before
+//! launch: JSFX, ReaScripts, FX chains, templates, helper extensions etc.
+
+use crate::{integration_test::Result, IntegrationError};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Subfolder of the REAPER resource directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceFolder {
+    Effects,
+    Scripts,
+    FXChains,
+    TrackTemplates,
+    ProjectTemplates,
+    UserPlugins,
+    /// Path, relative to the resource directory.
+    Custom(PathBuf),
+}
+impl ResourceFolder {
+    fn path(&self) -> &Path {
+        match self {
+            Self::Effects => Path::new("Effects"),
+            Self::Scripts => Path::new("Scripts"),
+            Self::FXChains => Path::new("FXChains"),
+            Self::TrackTemplates => Path::new("TrackTemplates"),
+            Self::ProjectTemplates => Path::new("ProjectTemplates"),
+            Self::UserPlugins => Path::new("UserPlugins"),
+            Self::Custom(path) => path,
+        }
+    }
+}
+
+/// File or directory, copied into a [`ResourceFolder`].
+///
+/// ```ignore
+/// use reaper_test::{IntegrationTestConfig, Resource, ResourceFolder};
+///
+/// IntegrationTestConfig::default()
+///     .resource(Resource::new("jsfx/my_filter", ResourceFolder::Effects))
+///     .resource(Resource::new("scripts", ResourceFolder::Scripts).rename("MyScripts"))
+///     .run();
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resource {
+    source: PathBuf,
+    folder: ResourceFolder,
+    name: Option<PathBuf>,
+}
+impl Resource {
+    /// Relative source path is resolved against the dir of the test crate.
+    pub fn new(source: impl Into<PathBuf>, folder: ResourceFolder) -> Self {
+        Self {
+            source: source.into(),
+            folder,
+            name: None,
+        }
+    }
+
+    /// Name inside the folder. Default is the name of the source.
+    pub fn rename(mut self, name: impl Into<PathBuf>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    fn source_path(&self, manifest_dir: &Path) -> PathBuf {
+        match self.source.is_absolute() {
+            true => self.source.clone(),
+            false => manifest_dir.join(&self.source),
+        }
+    }
+
+    /// Copies the resource into the resource directory.
+    pub(crate) fn install(&self, resource_path: &Path, manifest_dir: &Path) -> Result<()> {
+        let source = self.source_path(manifest_dir);
+        if !source.exists() {
+            return Err(IntegrationError::ResourceNotFound(source));
+        }
+        let name = match (&self.name, source.file_name()) {
+            (Some(name), _) => name.clone(),
+            (None, Some(name)) => PathBuf::from(name),
+            (None, None) => return Err(IntegrationError::ResourceNotFound(source)),
+        };
+        let target = resource_path.join(self.folder.path()).join(name);
+        println!("Installing {:?} to {:?}...", source, target);
+        copy_recursively(&source, &target)?;
+        Ok(())
+    }
+}
+
+fn copy_recursively(source: &Path, target: &Path) -> std::io::Result<()> {
+    if !source.is_dir() {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(source, target)?;
+        return Ok(());
+    }
+    fs::create_dir_all(target)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        copy_recursively(&entry.path(), &target.join(entry.file_name()))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,343 @@
+//! Runs the suite step by step, driven by REAPER timer.
+//!
+//! Sync steps are run one after another within a single tick. When a
+//! deferred step is pending, the rest of the suite waits for the next tick.
+
+use crate::{
+    deferred::{DeferredStep, StepContext, StepPoll},
+    panics::{install_hook, panic_details},
+    project,
+    report::{writer, ReportEvent},
+    Operation, PanicDetails, ReaperTest, StepOutcome, StepResult, TestCallback, TestStep,
+    TestStepResult,
+};
+use std::{
+    ffi::CStr,
+    panic::{self, AssertUnwindSafe},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// State of the suite between timer ticks.
+pub(crate) struct SuiteRun {
+    started: Instant,
+    next_step: usize,
+    any_selected: bool,
+    /// Failure of `before_all` hooks.
+    setup_error: Option<String>,
+    current: Option<RunningStep>,
+    results: Vec<StepResult>,
+}
+
+/// Deferred step, waiting for the next tick.
+struct RunningStep {
+    index: usize,
+    started: Instant,
+    context: StepContext,
+    operation: Box<dyn DeferredStep>,
+}
+
+enum StepState {
+    Finished(StepResult),
+    Pending(RunningStep),
+}
+
+fn outcome_of(result: thread::Result<TestStepResult>) -> StepOutcome {
+    match result {
+        Ok(Ok(())) => StepOutcome::Passed,
+        Ok(Err(reason)) => StepOutcome::Failed(reason.to_string()),
+        Err(payload) => StepOutcome::Panicked(panic_details(payload.as_ref())),
+    }
+}
+
+/// Calls the operation, catching panic.
+fn invoke(operation: &TestCallback) -> StepOutcome {
+    outcome_of(panic::catch_unwind(AssertUnwindSafe(|| {
+        operation(&mut ReaperTest::get_mut().reaper)
+    })))
+}
+
+/// Runs all hooks, even if some of them fail.
+///
+/// Returns description of failures, if any.
+fn run_hooks(kind: &str, hooks: &[Box<TestCallback>]) -> Option<String> {
+    let failures: Vec<String> = hooks
+        .iter()
+        .map(|hook| invoke(hook.as_ref()))
+        .filter(StepOutcome::is_failure)
+        .map(|outcome| format!("{} hook {}", kind, outcome))
+        .collect();
+    match failures.is_empty() {
+        true => None,
+        false => Some(failures.join("; ")),
+    }
+}
+
+impl ReaperTest {
+    /// `true` if the suite has been started and not finished yet.
+    pub(crate) fn is_running(&self) -> bool {
+        self.run.is_some()
+    }
+
+    /// Reports steps and runs `before_all` hooks.
+    ///
+    /// In list mode quits REAPER right after reporting, without starting
+    /// the suite.
+    pub(crate) fn start(&mut self) {
+        println!("# Testing reaper-rs\n");
+        install_hook();
+        let version = unsafe { CStr::from_ptr(self.reaper.low().GetAppVersion()) };
+        writer().emit(ReportEvent::SuiteStarted {
+            steps: self.steps.iter().map(TestStep::info).collect(),
+            reaper_version: Some(version.to_string_lossy().into_owned()),
+        });
+        if self.list_only {
+            writer().emit(ReportEvent::SuiteFinished {
+                duration_secs: 0.0,
+                setup_error: None,
+                teardown_error: None,
+            });
+            project::quit_reaper(&self.reaper);
+            return;
+        }
+        let started = Instant::now();
+        let any_selected = self
+            .steps
+            .iter()
+            .any(|step| self.filter.skip_outcome(step).is_none());
+        let setup_error = match any_selected {
+            true => run_hooks("before_all", &self.before_all),
+            false => None,
+        };
+        self.run = Some(SuiteRun {
+            started,
+            next_step: 0,
+            any_selected,
+            setup_error,
+            current: None,
+            results: Vec::new(),
+        });
+    }
+
+    /// Advances the suite by one timer tick.
+    ///
+    /// Returns `true` when the suite is finished.
+    pub(crate) fn tick(&mut self) -> bool {
+        if self.run.is_none() {
+            self.start();
+        }
+        let mut run = match self.run.take() {
+            Some(run) => run,
+            None => return true,
+        };
+        if let Some(step) = run.current.take() {
+            match self.poll_step(step) {
+                StepState::Finished(result) => run.results.push(result),
+                StepState::Pending(step) => {
+                    run.current = Some(step);
+                    self.run = Some(run);
+                    return false;
+                }
+            }
+        }
+        while run.next_step < self.steps.len() {
+            let index = run.next_step;
+            run.next_step += 1;
+            match self.start_step(index, run.setup_error.as_deref()) {
+                StepState::Finished(result) => run.results.push(result),
+                StepState::Pending(step) => {
+                    run.current = Some(step);
+                    self.run = Some(run);
+                    return false;
+                }
+            }
+        }
+        self.finish(run);
+        true
+    }
+
+    /// Runs sync step or polls deferred step for the first time.
+    ///
+    /// If `before_all` hooks failed, step is not run at all.
+    fn start_step(&self, index: usize, setup_error: Option<&str>) -> StepState {
+        let step = &self.steps[index];
+        if let Some(outcome) = self.filter.skip_outcome(step) {
+            return StepState::Finished(self.finish_step(index, Instant::now(), outcome, false));
+        }
+        println!("Testing step: {}", step.name);
+        writer().emit(ReportEvent::StepStarted {
+            name: step.name.clone(),
+            timeout_secs: self.step_timeout(index).map(|t| t.as_secs_f64()),
+        });
+        let started = Instant::now();
+        if let Some(error) = setup_error {
+            let outcome = StepOutcome::Failed(format!("not run: {}", error));
+            return StepState::Finished(self.finish_step(index, started, outcome, false));
+        }
+        if self.isolate_projects {
+            project::open_project_tab(&self.reaper);
+        }
+        if let Some(error) = run_hooks("before_each", &self.before_each) {
+            let outcome = StepOutcome::Failed(format!("not run: {}", error));
+            return StepState::Finished(self.finish_step(index, started, outcome, true));
+        }
+        match &step.operation {
+            Operation::Sync(operation) => {
+                let outcome = match (invoke(operation.as_ref()), self.step_timeout(index)) {
+                    (StepOutcome::Passed, Some(limit)) if started.elapsed() > limit => {
+                        StepOutcome::TimedOut(limit)
+                    }
+                    (outcome, _) => outcome,
+                };
+                StepState::Finished(self.finish_step(index, started, outcome, true))
+            }
+            Operation::Deferred(make) => self.poll_step(RunningStep {
+                index,
+                started,
+                context: StepContext::new(),
+                operation: make(),
+            }),
+        }
+    }
+
+    fn poll_step(&self, mut step: RunningStep) -> StepState {
+        let poll = panic::catch_unwind(AssertUnwindSafe(|| {
+            step.operation
+                .poll(&mut ReaperTest::get_mut().reaper, &step.context)
+        }));
+        step.context.advance();
+        let outcome = match poll {
+            Ok(StepPoll::Pending) => match self.step_timeout(step.index) {
+                Some(limit) if step.started.elapsed() > limit => StepOutcome::TimedOut(limit),
+                _ => return StepState::Pending(step),
+            },
+            Ok(StepPoll::Ready(result)) => outcome_of(Ok(result)),
+            Err(payload) => outcome_of(Err(payload)),
+        };
+        StepState::Finished(self.finish_step(step.index, step.started, outcome, true))
+    }
+
+    fn step_timeout(&self, index: usize) -> Option<Duration> {
+        self.steps[index].timeout.or(self.default_timeout)
+    }
+
+    /// Runs `after_each` hooks, if step has been set up, and reports
+    /// the result.
+    fn finish_step(
+        &self,
+        index: usize,
+        started: Instant,
+        outcome: StepOutcome,
+        set_up: bool,
+    ) -> StepResult {
+        let name = self.steps[index].name.clone();
+        let mut teardown_error = match set_up {
+            true => run_hooks("after_each", &self.after_each),
+            false => None,
+        };
+        if set_up && self.isolate_projects {
+            if let Err(error) = project::close_project_tab(&self.reaper) {
+                let error = format!("project tab is not closed: {}", error);
+                teardown_error = Some(match teardown_error {
+                    Some(hooks_error) => format!("{}; {}", hooks_error, error),
+                    None => error,
+                });
+            }
+        }
+        let duration = match outcome {
+            StepOutcome::Ignored | StepOutcome::FilteredOut => Duration::ZERO,
+            _ => started.elapsed(),
+        };
+        writer().emit(ReportEvent::StepFinished {
+            name: name.clone(),
+            outcome: outcome.clone(),
+            duration_secs: duration.as_secs_f64(),
+            teardown_error: teardown_error.clone(),
+        });
+        StepResult {
+            name,
+            outcome,
+            duration,
+            teardown_error,
+        }
+    }
+
+    /// Runs `after_all` hooks, reports the suite result and quits REAPER
+    /// if run under integration test.
+    ///
+    /// The launcher judges the result only by the report, so REAPER is
+    /// asked to quit the usual way, letting it run its own shutdown and
+    /// destroy hooks of plugins.
+    fn finish(&mut self, run: SuiteRun) {
+        let teardown_error = match run.any_selected {
+            true => run_hooks("after_all", &self.after_all),
+            false => None,
+        };
+        writer().emit(ReportEvent::SuiteFinished {
+            duration_secs: run.started.elapsed().as_secs_f64(),
+            setup_error: run.setup_error.clone(),
+            teardown_error: teardown_error.clone(),
+        });
+        let results = run.results;
+        let failed = Self::print_summary(&results);
+        for error in run.setup_error.iter().chain(teardown_error.iter()) {
+            println!("{}", error);
+        }
+        match failed == 0 && run.setup_error.is_none() && teardown_error.is_none() {
+            true => {
+                println!("From REAPER: reaper-rs integration test executed successfully");
+                if self.is_integration_test {
+                    project::quit_reaper(&self.reaper);
+                }
+            }
+            false => {
+                let reason = format!("{} of {} steps failed", failed, results.len());
+                match self.is_integration_test {
+                    true => {
+                        eprintln!("From REAPER: reaper-rs integration test failed: {}", reason);
+                        project::quit_reaper(&self.reaper);
+                    }
+                    false => panic!("From REAPER: reaper-rs integration test failed: {}", reason),
+                }
+            }
+        }
+    }
+
+    /// Prints outcome of every step and returns number of failed steps.
+    fn print_summary(results: &[StepResult]) -> usize {
+        println!("\n# Summary\n");
+        for result in results {
+            println!(
+                "{} ... {} ({:.3}s)",
+                result.name,
+                result.outcome,
+                result.duration.as_secs_f64()
+            );
+            if let StepOutcome::Panicked(PanicDetails {
+                backtrace: Some(backtrace),
+                ..
+            }) = &result.outcome
+            {
+                println!("{}", backtrace);
+            }
+            if let Some(error) = &result.teardown_error {
+                println!("    teardown: {}", error);
+            }
+        }
+        let count =
+            |outcome: &StepOutcome| results.iter().filter(|r| &r.outcome == outcome).count();
+        let failed = results
+            .iter()
+            .filter(|r| r.outcome.is_failure() || r.teardown_error.is_some())
+            .count();
+        println!(
+            "\ntest result: {}. {} passed; {} failed; {} ignored; {} filtered out\n",
+            if failed == 0 { "ok" } else { "FAILED" },
+            count(&StepOutcome::Passed),
+            failed,
+            count(&StepOutcome::Ignored),
+            count(&StepOutcome::FilteredOut)
+        );
+        failed
+    }
+}
@@ -0,0 +1,335 @@
+//! REAPER versions and where to get them.
+//!
+//! Archive name, layout and executable path are computed from the
+//! version number and the host architecture, so any release can be pinned.
+
+use std::{fmt::Display, path::PathBuf, str::FromStr};
+
+/// Version of REAPER, e.g. `7.15`.
+///
+/// ```ignore
+/// let version = ReaperVersion::new(7, 15);
+/// assert_eq!(version, "7.15".parse().unwrap());
+/// ```
+///
+/// Versions are compared only by number: expected digest of the archive
+/// does not make another version.
+#[derive(Debug, Clone, Copy)]
+pub struct ReaperVersion {
+    major: u32,
+    minor: u32,
+    sha256: Option<[u8; 32]>,
+}
+impl ReaperVersion {
+    #[deprecated(note = "it has always been REAPER 6.71, use ReaperVersion::new(6, 71)")]
+    pub const V7_71: Self = Self::new(6, 71);
+    #[deprecated(note = "it has always been REAPER 6.73, use ReaperVersion::new(6, 73)")]
+    pub const V7_73: Self = Self::new(6, 73);
+
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self {
+            major,
+            minor,
+            sha256: None,
+        }
+    }
+
+    /// Expected SHA-256 digest (hex) of the archive for this platform.
+    ///
+    /// Archive is verified before it is unpacked. Digests can be also
+    /// given by [`IntegrationTestConfig::checksum_file`](crate::IntegrationTestConfig::checksum_file).
+    ///
+    /// # Panics
+    ///
+    /// If digest is not 64 hex digits.
+    pub fn with_sha256(mut self, digest: &str) -> Self {
+        self.sha256 = Some(
+            parse_sha256(digest).unwrap_or_else(|| panic!("{:?} is not a SHA-256 digest", digest)),
+        );
+        self
+    }
+
+    pub fn sha256(&self) -> Option<[u8; 32]> {
+        self.sha256
+    }
+
+    /// The latest version, this crate is tested with.
+    pub fn latest() -> Self {
+        Self::new(7, 22)
+    }
+
+    pub fn major(&self) -> u32 {
+        self.major
+    }
+
+    pub fn minor(&self) -> u32 {
+        self.minor
+    }
+
+    /// Version as it is written in the names of REAPER archives, e.g.
+    /// `715`.
+    fn file_version(&self) -> String {
+        format!("{}{:02}", self.major, self.minor)
+    }
+
+    /// Path of the archive relative to the `files` directory of the
+    /// site, e.g. `7.x/reaper715_linux_x86_64.tar.xz`.
+    pub(crate) fn archive_path(&self, archive_name: &str) -> String {
+        format!("{}.x/{}", self.major, archive_name)
+    }
+
+    fn linux_arch() -> &'static str {
+        match std::env::consts::ARCH {
+            "aarch64" => "aarch64",
+            "arm" => "armv7l",
+            "x86" => "i686",
+            _ => "x86_64",
+        }
+    }
+
+    /// Architecture of the macOS build. Since REAPER 7 only universal
+    /// builds are published.
+    fn macos_arch(&self) -> &'static str {
+        match (self.major >= 7, std::env::consts::ARCH) {
+            (true, _) => "universal",
+            (false, "aarch64") => "arm64",
+            (false, _) => "x86_64",
+        }
+    }
+
+    pub(crate) fn linux_archive_name(&self) -> String {
+        format!(
+            "reaper{}_linux_{}.tar.xz",
+            self.file_version(),
+            Self::linux_arch()
+        )
+    }
+
+    pub(crate) fn macos_archive_name(&self) -> String {
+        format!("reaper{}_{}.dmg", self.file_version(), self.macos_arch())
+    }
+
+    /// REAPER home inside the unpacked tarball.
+    pub(crate) fn linux_download_path(&self) -> PathBuf {
+        PathBuf::from(format!("reaper_linux_{}/REAPER", Self::linux_arch()))
+    }
+
+    pub(crate) fn macos_download_path(&self) -> PathBuf {
+        PathBuf::from(format!("reaper_macos_{}", self.macos_arch()))
+    }
+
+    pub(crate) fn linux_executable_path(&self) -> PathBuf {
+        PathBuf::from("reaper")
+    }
+
+    pub(crate) fn macos_executable_path(&self) -> PathBuf {
+        PathBuf::from("REAPER.app/Contents/MacOS/REAPER")
+    }
+
+    /// Application bundle, relative to the mount point of the dmg.
+    pub(crate) fn macos_app_in_dmg(&self) -> PathBuf {
+        PathBuf::from("REAPER.app")
+    }
+}
+impl PartialEq for ReaperVersion {
+    fn eq(&self, other: &Self) -> bool {
+        (self.major, self.minor) == (other.major, other.minor)
+    }
+}
+impl Eq for ReaperVersion {}
+impl PartialOrd for ReaperVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for ReaperVersion {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.major, self.minor).cmp(&(other.major, other.minor))
+    }
+}
+impl std::hash::Hash for ReaperVersion {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (self.major, self.minor).hash(state);
+    }
+}
+impl Display for ReaperVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{:02}", self.major, self.minor)
+    }
+}
+impl FromStr for ReaperVersion {
+    type Err = ParseVersionError;
+
+    /// Parses `major.minor`, e.g. `7.15`, `7.01` or `6.0`.
+    ///
+    /// Minor is written by two digits as in REAPER itself. Single digit
+    /// is accepted only for `0`: `7.1` is rejected, since it may be meant
+    /// as both `7.01` and `7.10`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseVersionError(s.to_string());
+        let (major, minor) = s.trim().split_once('.').ok_or_else(error)?;
+        let is_number = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+        if !is_number(major) || !is_number(minor) || !(minor.len() == 2 || minor == "0") {
+            return Err(error());
+        }
+        Ok(Self::new(
+            major.parse().map_err(|_| error())?,
+            minor.parse().map_err(|_| error())?,
+        ))
+    }
+}
+
+/// Parses 64 hex digits.
+pub(crate) fn parse_sha256(hex: &str) -> Option<[u8; 32]> {
+    let hex = hex.trim();
+    // `from_str_radix` alone would also accept sign.
+    if hex.len() != 64 || !hex.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut digest = [0; 32];
+    for (idx, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[idx * 2..idx * 2 + 2], 16).ok()?;
+    }
+    Some(digest)
+}
+
+pub(crate) fn sha256_to_hex(digest: &[u8; 32]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// String is not a REAPER version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseVersionError(pub String);
+impl Display for ParseVersionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:?} is not a REAPER version, expected e.g. \"7.15\"",
+            self.0
+        )
+    }
+}
+impl std::error::Error for ParseVersionError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display() {
+        assert_eq!("7.15".parse(), Ok(ReaperVersion::new(7, 15)));
+        assert_eq!(" 7.01 ".parse(), Ok(ReaperVersion::new(7, 1)));
+        assert_eq!("6.0".parse(), Ok(ReaperVersion::new(6, 0)));
+        assert_eq!(ReaperVersion::new(7, 1).to_string(), "7.01");
+        assert_eq!(ReaperVersion::new(6, 0).to_string(), "6.00");
+        assert_eq!("6.00".parse::<ReaperVersion>().unwrap().to_string(), "6.00");
+    }
+
+    #[test]
+    fn parse_rejects_ambiguous_and_malformed() {
+        for input in [
+            "7.1", "7.100", "7", "7.", ".15", "7.+5", "-7.15", "v7.15", "7.15.1",
+        ] {
+            assert_eq!(
+                input.parse::<ReaperVersion>(),
+                Err(ParseVersionError(input.to_string())),
+                "{}",
+                input
+            );
+        }
+    }
+
+    const DIGEST: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    #[test]
+    fn parse_sha256_digest() {
+        let digest = parse_sha256(DIGEST).unwrap();
+        assert_eq!(digest[0], 0x9f);
+        assert_eq!(digest[31], 0x08);
+        assert_eq!(sha256_to_hex(&digest), DIGEST);
+        assert_eq!(
+            parse_sha256(&format!(" {} ", DIGEST.to_uppercase())),
+            Some(digest)
+        );
+        assert_eq!(parse_sha256(&DIGEST[1..]), None);
+        assert_eq!(parse_sha256(&format!("{}0", DIGEST)), None);
+        assert_eq!(parse_sha256(&DIGEST.replace('f', "x")), None);
+        assert_eq!(parse_sha256(&"+f".repeat(32)), None);
+        // 64 bytes, but not 64 hex digits.
+        assert_eq!(parse_sha256(&format!("é{}", &DIGEST[2..])), None);
+    }
+
+    #[test]
+    fn digest_does_not_change_identity() {
+        use std::collections::HashSet;
+        let pinned = ReaperVersion::new(7, 15).with_sha256(DIGEST);
+        assert_eq!(pinned, ReaperVersion::new(7, 15));
+        assert_eq!(
+            pinned.cmp(&ReaperVersion::new(7, 15)),
+            std::cmp::Ordering::Equal
+        );
+        assert!(ReaperVersion::new(6, 73) < pinned);
+        assert!(pinned < ReaperVersion::new(7, 22));
+        let versions: HashSet<_> = [pinned, ReaperVersion::new(7, 15)].into_iter().collect();
+        assert_eq!(versions.len(), 1);
+    }
+
+    #[test]
+    fn archive_path_is_grouped_by_major() {
+        let version = ReaperVersion::new(6, 73);
+        assert_eq!(
+            version.archive_path("reaper673_linux_x86_64.tar.xz"),
+            "6.x/reaper673_linux_x86_64.tar.xz"
+        );
+        assert_eq!(
+            ReaperVersion::new(7, 22).archive_path("reaper722_universal.dmg"),
+            "7.x/reaper722_universal.dmg"
+        );
+    }
+
+    #[test]
+    fn macos_archives_are_universal_since_7() {
+        assert_eq!(
+            ReaperVersion::new(7, 22).macos_archive_name(),
+            "reaper722_universal.dmg"
+        );
+        assert_eq!(
+            ReaperVersion::new(7, 1).macos_archive_name(),
+            "reaper701_universal.dmg"
+        );
+        assert_eq!(
+            ReaperVersion::new(7, 22).macos_download_path(),
+            PathBuf::from("reaper_macos_universal")
+        );
+    }
+
+    #[test]
+    #[cfg(target_arch = "x86_64")]
+    fn archive_names_on_x86_64() {
+        let cases = [
+            (
+                ReaperVersion::new(6, 73),
+                "reaper673_linux_x86_64.tar.xz",
+                "reaper673_x86_64.dmg",
+            ),
+            (
+                ReaperVersion::new(7, 22),
+                "reaper722_linux_x86_64.tar.xz",
+                "reaper722_universal.dmg",
+            ),
+            (
+                ReaperVersion::new(7, 1),
+                "reaper701_linux_x86_64.tar.xz",
+                "reaper701_universal.dmg",
+            ),
+        ];
+        for (version, linux, macos) in cases {
+            assert_eq!(version.linux_archive_name(), linux);
+            assert_eq!(version.macos_archive_name(), macos);
+        }
+        assert_eq!(
+            ReaperVersion::new(6, 73).linux_download_path(),
+            PathBuf::from("reaper_linux_x86_64/REAPER")
+        );
+    }
+}
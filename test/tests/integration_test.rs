@@ -1,6 +1,5 @@
-use reaper_test::{run_integration_test, ReaperVersion};
+use reaper_test::{run_integration_test_harness, ReaperVersion};
 
-#[test]
 fn main() {
-    run_integration_test(ReaperVersion::latest());
+    run_integration_test_harness(ReaperVersion::latest());
 }